@@ -24,7 +24,7 @@ use euclid::default::Transform2D;
 use svg::node::element::path::Data;
 use svg::node::element::Path;
 use svg::Document;
-use tiling::{FiveFold, MatchList, Shape, Tiling};
+use tiling::{FiveFold, MatchList, PentagonalList, Shape, Tiling};
 
 fn draw<T: Shape<N>, const N: usize>(shape: T, colour: &str, transform: &Transform2D<f64>) -> Path {
     // array iteration by value doesn't resolve properly until Edition 2021 rolls around
@@ -42,28 +42,51 @@ fn draw<T: Shape<N>, const N: usize>(shape: T, colour: &str, transform: &Transfo
         .set("d", data)
 }
 
+fn document(paths: impl Iterator<Item = Path>, bounds: &Box2D<f64>) -> Document {
+    paths.fold(
+        Document::new().set(
+            "viewbox",
+            (bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y),
+        ),
+        |doc, path| doc.add(path),
+    )
+}
+
 fn render(matches: MatchList, bounds: &Box2D<f64>, scale: f64) -> Document {
     let transform = Transform2D::translation(-bounds.min.x, -bounds.min.y).then_scale(scale, scale);
     let bounds = bounds
         .translate((-bounds.min.x, -bounds.min.y).into())
         .scale(scale, scale);
-    matches
-        .darts
-        .into_iter()
-        .map(|dart| draw(dart, "red", &transform))
-        .chain(
-            matches
-                .kites
-                .into_iter()
-                .map(|kite| draw(kite, "green", &transform)),
-        )
-        .fold(
-            Document::new().set(
-                "viewbox",
-                (bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y),
+    document(
+        matches
+            .darts
+            .into_iter()
+            .map(|dart| draw(dart, "red", &transform))
+            .chain(
+                matches
+                    .kites
+                    .into_iter()
+                    .map(|kite| draw(kite, "green", &transform)),
             ),
-            |doc, path| doc.add(path),
-        )
+        &bounds,
+    )
+}
+
+fn render_pentagonal(tiles: PentagonalList, bounds: &Box2D<f64>, scale: f64) -> Document {
+    let transform = Transform2D::translation(-bounds.min.x, -bounds.min.y).then_scale(scale, scale);
+    let bounds = bounds
+        .translate((-bounds.min.x, -bounds.min.y).into())
+        .scale(scale, scale);
+    document(
+        (tiles.pentagons.into_iter())
+            .map(|pentagon| draw(pentagon, "orange", &transform))
+            .chain(
+                (tiles.pentagrams.into_iter()).map(|pentagram| draw(pentagram, "blue", &transform)),
+            )
+            .chain((tiles.boats.into_iter()).map(|boat| draw(boat, "purple", &transform)))
+            .chain((tiles.diamonds.into_iter()).map(|diamond| draw(diamond, "yellow", &transform))),
+        &bounds,
+    )
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -82,11 +105,19 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut tiling = Tiling::new(plane, bounds);
     let matches = tiling.compute_area();
 
-    svg::save(
-        format!("{}.svg", configuration),
-        &render(matches, &bounds, 20.),
-    )
-    .unwrap();
+    match std::env::args().nth(2).as_deref() {
+        None | Some("p2") => svg::save(
+            format!("{}.svg", configuration),
+            &render(matches, &bounds, 20.),
+        )
+        .unwrap(),
+        Some("p1") => svg::save(
+            format!("{}-p1.svg", configuration),
+            &render_pentagonal(matches.pentagonal(), &bounds, 20.),
+        )
+        .unwrap(),
+        _ => panic!("Invalid tiling selection"),
+    }
 
     Ok(())
 }
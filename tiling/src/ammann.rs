@@ -0,0 +1,127 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ops::RangeInclusive;
+
+use euclid::default::{Box2D, Point2D, Vector2D};
+
+use super::constants::epsilon;
+use super::musical_sequence::BarNumber;
+
+/// A single bar of one of the five musical sequences.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AmmannBar {
+    /// The index of the sequence the bar belongs to.
+    pub sequence: usize,
+    pub bar: BarNumber,
+    /// Whether the bar has been decided yet; only forced bars bound tiles.
+    pub forced: bool,
+    pub start: Point2D<f64>,
+    pub end: Point2D<f64>,
+}
+
+/// The area over which a run of unforced bars of one sequence could still fall, clipped to the
+/// bounds it was found in. Tiles there depend on how the bars are forced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndeterminedRegion {
+    pub sequence: usize,
+    pub bars: RangeInclusive<BarNumber>,
+    pub polygon: Vec<Point2D<f64>>,
+}
+
+// clips the bounds to the strip where `along` lies between `near` and `far`
+pub(crate) fn clip_strip(
+    bounds: &Box2D<f64>,
+    along: impl Fn(Point2D<f64>) -> f64,
+    near: f64,
+    far: f64,
+) -> Vec<Point2D<f64>> {
+    let corners = vec![
+        bounds.min,
+        Point2D::new(bounds.max.x, bounds.min.y),
+        bounds.max,
+        Point2D::new(bounds.min.x, bounds.max.y),
+    ];
+
+    // sutherland-hodgman, once for each side of the strip
+    [(near, 1f64), (far, -1f64)]
+        .iter()
+        .fold(corners, |polygon, &(edge, sign)| {
+            let inside = |point: Point2D<f64>| sign * (along(point) - edge) >= 0f64;
+            let mut clipped = Vec::new();
+            for (i, &a) in polygon.iter().enumerate() {
+                let b = polygon[(i + 1) % polygon.len()];
+                if inside(a) {
+                    clipped.push(a);
+                }
+                if inside(a) != inside(b) {
+                    let t = (edge - along(a)) / (along(b) - along(a));
+                    clipped.push(a.lerp(b, t));
+                }
+            }
+            clipped
+        })
+}
+
+// clips the infinite line through `point` along `direction` to `bounds`
+pub(crate) fn clip(
+    point: Point2D<f64>,
+    direction: Vector2D<f64>,
+    bounds: &Box2D<f64>,
+) -> Option<(Point2D<f64>, Point2D<f64>)> {
+    let mut first = f64::NEG_INFINITY;
+    let mut last = f64::INFINITY;
+
+    for (p, d, min, max) in [
+        (point.x, direction.x, bounds.min.x, bounds.max.x),
+        (point.y, direction.y, bounds.min.y, bounds.max.y),
+    ] {
+        if d.abs() < epsilon::<f64>() {
+            if p < min || p > max {
+                return None;
+            }
+        } else {
+            let (a, b) = ((min - p) / d, (max - p) / d);
+            first = first.max(a.min(b));
+            last = last.min(a.max(b));
+        }
+    }
+
+    (first < last).then(|| (point + direction * first, point + direction * last))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clip_diagonal() {
+        let bounds = Box2D::new(Point2D::new(-1f64, -1f64), Point2D::new(1f64, 1f64));
+
+        let (start, end) = clip(Point2D::origin(), Vector2D::new(1f64, 1f64), &bounds).unwrap();
+        assert!(start.distance_to(Point2D::new(-1f64, -1f64)) < epsilon::<f64>());
+        assert!(end.distance_to(Point2D::new(1f64, 1f64)) < epsilon::<f64>());
+
+        let (start, end) =
+            clip(Point2D::new(0.5, 0f64), Vector2D::new(0f64, 1f64), &bounds).unwrap();
+        assert!(start.distance_to(Point2D::new(0.5, -1f64)) < epsilon::<f64>());
+        assert!(end.distance_to(Point2D::new(0.5, 1f64)) < epsilon::<f64>());
+
+        assert!(clip(Point2D::new(3f64, 0f64), Vector2D::new(1f64, 1f64), &bounds).is_none());
+    }
+}
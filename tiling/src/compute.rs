@@ -0,0 +1,142 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use super::constellation::Forcing;
+use super::double_kite::DoubleKite;
+use super::tiling::MatchList;
+use super::verify::Violation;
+
+/// How far a computation has got, as reported after each search of the area.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Progress {
+    /// Searches of the area so far, counting this one.
+    pub iteration: usize,
+    /// Intersection points in the area.
+    pub points: usize,
+    /// Kites and darts found by this search, each counted once.
+    pub tiles: usize,
+}
+
+/// What a computation did, for sizing areas.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct ComputeStats {
+    /// Searches of the area.
+    pub iterations: usize,
+    /// Intersection points the plane worked out, rather than having cached already.
+    pub points_generated: usize,
+    /// Intersection points cached by the plane at the end.
+    pub cache_size: usize,
+    /// Pairs of points at the key pair distance of darts, tested over every search.
+    pub dart_pairs: usize,
+    /// As for darts, for double kites.
+    pub double_kite_pairs: usize,
+    /// As for darts, for kites.
+    pub kite_pairs: usize,
+    /// Bars forced.
+    pub forcings: usize,
+    /// Time spent working out and gathering intersection points.
+    pub points_time: Duration,
+    /// Time spent searching for constellations.
+    pub search_time: Duration,
+    /// Time spent forcing bars.
+    pub forcing_time: Duration,
+    /// Time spent in total, including removing duplicate tiles.
+    pub total_time: Duration,
+}
+
+/// The tiles of a computed area, with what else was found and done while finding them.
+#[derive(Debug, Clone, Default)]
+pub struct Computation {
+    pub matches: MatchList,
+    /// The double kites found while computing the tiles, which force bars but aren't tiles
+    /// themselves.
+    pub double_kites: Vec<DoubleKite>,
+    /// Every bar forced while computing the tiles, in order.
+    pub forcings: Vec<Forcing>,
+    /// How computing the tiles went.
+    pub stats: ComputeStats,
+}
+
+/// Limits on a computation, and a way to watch it. Every limit is off by default.
+#[derive(Default)]
+pub struct ComputeOptions<'a> {
+    /// The most searches of the area to make. With none, the plane is left as it is.
+    pub max_iterations: Option<usize>,
+    /// Called after each search of the area.
+    pub progress: Option<Box<dyn FnMut(Progress) + 'a>>,
+    /// Stops the computation after the current search once set.
+    pub cancel: Option<&'a AtomicBool>,
+    /// Stops the computation after the search during which the time runs out.
+    pub time_budget: Option<Duration>,
+}
+
+/// A computation stopped before the plane stopped forcing, with the tiles of its last search. The
+/// bars forced after that search stay forced.
+#[derive(Debug, Clone)]
+pub enum ComputeError {
+    Iterations(Box<Computation>),
+    Cancelled(Box<Computation>),
+    TimedOut(Box<Computation>),
+    /// The turned copies of a symmetric plane's sector don't fit together along the seams between
+    /// them, with the ways in which they don't. The computation itself finished.
+    Seams(Box<Computation>, Vec<Violation>),
+}
+
+impl ComputeError {
+    /// What was found before stopping.
+    pub fn partial(self) -> Computation {
+        match self {
+            ComputeError::Iterations(partial)
+            | ComputeError::Cancelled(partial)
+            | ComputeError::TimedOut(partial)
+            | ComputeError::Seams(partial, _) => *partial,
+        }
+    }
+
+    // the same error, with what was found passed through `f`
+    pub(crate) fn map_partial(self, f: impl FnOnce(Computation) -> Computation) -> ComputeError {
+        match self {
+            ComputeError::Iterations(partial) => ComputeError::Iterations(Box::new(f(*partial))),
+            ComputeError::Cancelled(partial) => ComputeError::Cancelled(Box::new(f(*partial))),
+            ComputeError::TimedOut(partial) => ComputeError::TimedOut(Box::new(f(*partial))),
+            ComputeError::Seams(partial, violations) => {
+                ComputeError::Seams(Box::new(f(*partial)), violations)
+            }
+        }
+    }
+}
+
+impl Display for ComputeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComputeError::Iterations(_) => write!(f, "iteration limit reached"),
+            ComputeError::Cancelled(_) => write!(f, "computation cancelled"),
+            ComputeError::TimedOut(_) => write!(f, "time budget exhausted"),
+            ComputeError::Seams(_, violations) => {
+                write!(f, "{} violations along the seams", violations.len())
+            }
+        }
+    }
+}
+
+impl Error for ComputeError {}
@@ -28,9 +28,12 @@ use crate::fivefold::bar_num;
 
 use super::constants::epsilon;
 use super::fivefold::FiveFold;
-use super::intersection_point::IntersectionPoint;
+use super::intersection_point::{Crossing, IntersectionPoint};
+use super::musical_sequence::{BarBound, BarNumber};
 
-pub(crate) type PointGraph<'a> = BTreeMap<&'a IntersectionPoint, BTreeSet<&'a IntersectionPoint>>;
+/// Intersection points paired with the points at some distance from them, as scanned for
+/// constellations.
+pub type PointGraph<'a> = BTreeMap<&'a IntersectionPoint, BTreeSet<&'a IntersectionPoint>>;
 
 trait Consume {
     fn consume(&mut self, other: Self);
@@ -97,7 +100,9 @@ fn transform(real: &[IntersectionPoint; 2], test: [&IntersectionPoint; 2]) -> Tr
         .pre_translate(Vector2D::new(-real[0].x(), -real[0].y()))
 }
 
-pub(crate) fn test_required(
+/// Maps the key pair of a pattern onto a pair of points, returning the mapping if every point of
+/// the pattern lands on an intersection point of the same two sequences.
+pub fn test_required(
     points: &BTreeSet<&IntersectionPoint>,
     plane: &FiveFold,
     pair: [&IntersectionPoint; 2],
@@ -127,7 +132,28 @@ pub(crate) fn test_required(
     Some(map)
 }
 
-pub(crate) fn map_optional(
+/// The crossings a pattern lands on under the mapping, if it lands on one for every point.
+pub(crate) fn crossings<const N: usize>(
+    pattern: &[IntersectionPoint],
+    mapping: &Transform2D<f64>,
+    plane: &FiveFold,
+) -> Option<[Crossing; N]> {
+    pattern
+        .iter()
+        .map(|point| {
+            plane
+                .intersection_point(mapping.transform_point(point.point()))
+                .and_then(|point| point.crossing())
+        })
+        .collect::<Option<ArrayVec<_, N>>>()?
+        .into_inner()
+        .ok()
+}
+
+/// Maps an optional point of a pattern, which is either an intersection point or a point on a
+/// single forced bar. In the latter case, the point carries the sequence `amount` sequences on
+/// from the forced one, which is the one to force through it.
+pub fn map_optional(
     point: &IntersectionPoint,
     map: &Transform2D<f64>,
     plane: &FiveFold,
@@ -163,6 +189,43 @@ pub(crate) fn map_optional(
     }
 }
 
+/// Which constellation forced a bar, and through which of its points.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ForcingSource {
+    DartLeft,
+    DartRight,
+    DoubleKite,
+    /// A constellation from outside this crate.
+    Other(&'static str),
+}
+
+/// A bar forced while computing a tiling.
+#[derive(Debug, Copy, Clone)]
+pub struct Forcing {
+    pub source: ForcingSource,
+    /// The mapping of the constellation responsible.
+    pub mapping: Transform2D<f64>,
+    pub sequence: usize,
+    pub bar: BarNumber,
+    pub bound: BarBound,
+}
+
+impl Forcing {
+    pub fn new(
+        source: ForcingSource,
+        mapping: Transform2D<f64>,
+        (sequence, bar, bound): (usize, BarNumber, BarBound),
+    ) -> Self {
+        Self {
+            source,
+            mapping,
+            sequence,
+            bar,
+            bound,
+        }
+    }
+}
+
 pub trait Constellation {
     fn delta() -> f64;
     fn key_pair() -> &'static [IntersectionPoint; 2];
@@ -177,14 +240,17 @@ pub trait Constellation {
 
     fn mapping(&self) -> Transform2D<f64>;
 
-    fn force_bars(&self, plane: &mut FiveFold) -> bool;
+    fn force_bars(&self, plane: &mut FiveFold) -> Option<Forcing>;
 
+    /// Finds the constellations among the points, returning how many pairs of points at the key
+    /// pair's distance were tested.
     fn constellations(
         points: &BTreeSet<&IntersectionPoint>,
         plane: &FiveFold,
         boundaries: Option<&[&IntersectionPoint]>,
         constellations: &mut Vec<Self>,
-    ) where
+    ) -> usize
+    where
         Self: Sized,
     {
         let pairs = if let Some(boundaries) = boundaries.filter(|boundaries| boundaries.len() >= 2)
@@ -220,16 +286,19 @@ pub trait Constellation {
             pair_scan(points.iter().copied(), Self::delta())
         };
 
+        let mut tested = 0;
         for (primary, secondary) in pairs
             .iter()
             .flat_map(|(primary, secondaries)| secondaries.iter().map(move |s| (primary, s)))
         {
+            tested += 1;
             if let Some(found) = Self::test_pair(points, plane, [primary, secondary]) {
                 constellations.push(found);
             } else if let Some(found) = Self::test_pair(points, plane, [secondary, primary]) {
                 constellations.push(found);
             }
         }
+        tested
     }
 }
 
@@ -27,9 +27,12 @@ use lazy_static::lazy_static;
 use crate::constants::{minnick_x, minnick_y};
 use crate::shape::Shape;
 
-use super::constellation::{map_optional, test_required, Constellation};
+use super::constellation::{
+    crossings, map_optional, test_required, Constellation, Forcing, ForcingSource,
+};
 use super::fivefold::{intersection_point, FiveFold};
-use super::intersection_point::IntersectionPoint;
+use super::intersection_point::{fifth_turns, Crossing, IntersectionPoint};
+use super::musical_sequence::MusicalSequence;
 use super::shape::Triangle;
 
 lazy_static! {
@@ -79,10 +82,11 @@ pub struct Dart {
     mapping: Transform2D<f64>,
     left: Option<IntersectionPoint>,
     right: Option<IntersectionPoint>,
+    crossings: Option<[Crossing; 3]>,
 }
 
 impl Dart {
-    fn new(
+    pub(crate) fn new(
         mapping: Transform2D<f64>,
         left: Option<IntersectionPoint>,
         right: Option<IntersectionPoint>,
@@ -91,6 +95,42 @@ impl Dart {
             mapping,
             left,
             right,
+            crossings: None,
+        }
+    }
+
+    /// The left optional point of the pattern which produced the dart: where two bars cross or, if
+    /// only one bar is forced there, a point on it carrying the sequence to force through it
+    /// instead. Missing if neither is forced, or the dart wasn't found in a plane.
+    pub fn left(&self) -> Option<&IntersectionPoint> {
+        self.left.as_ref()
+    }
+
+    /// The right optional point of the pattern which produced the dart, as for [`Dart::left`].
+    pub fn right(&self) -> Option<&IntersectionPoint> {
+        self.right.as_ref()
+    }
+
+    /// The crossings of the bars which produced the dart, if it was found in a plane.
+    pub fn crossings(&self) -> Option<[Crossing; 3]> {
+        self.crossings
+    }
+
+    /// Whether an optional point lies where the plane can no longer have a bar.
+    pub(crate) fn contradicts(&self, plane: &FiveFold) -> bool {
+        (self.left.iter())
+            .chain(self.right.iter())
+            .filter(|point| point.seq2().is_none())
+            .any(|point| (point.seq1()).is_some_and(|ms| plane.contradicts(point.point(), &ms)))
+    }
+
+    pub(crate) fn rotated(&self, turns: usize, sequences: &[MusicalSequence]) -> Dart {
+        let rotated = |point: &IntersectionPoint| point.rotated(turns, sequences);
+        Dart {
+            mapping: self.mapping.then(&fifth_turns(turns)),
+            left: self.left.as_ref().map(rotated),
+            right: self.right.as_ref().map(rotated),
+            crossings: (self.crossings).map(|crossings| crossings.map(|c| c.rotated(turns))),
         }
     }
 }
@@ -101,7 +141,7 @@ impl Constellation for Dart {
     }
 
     fn key_pair() -> &'static [IntersectionPoint; 2] {
-        &*KEY_PAIR
+        &KEY_PAIR
     }
 
     fn pattern() -> &'static [IntersectionPoint] {
@@ -119,10 +159,13 @@ impl Constellation for Dart {
         let mapping = test_required(points, plane, pair, Self::key_pair(), Self::pattern());
 
         mapping.and_then(|mapping| {
-            let left = map_optional(&*OPTIONAL_LEFT, &mapping, plane, 4);
-            let right = map_optional(&*OPTIONAL_RIGHT, &mapping, plane, 1);
+            let left = map_optional(&OPTIONAL_LEFT, &mapping, plane, 4);
+            let right = map_optional(&OPTIONAL_RIGHT, &mapping, plane, 1);
 
-            (left.is_some() || right.is_some()).then(|| Dart::new(mapping, left, right))
+            (left.is_some() || right.is_some()).then(|| Dart {
+                crossings: crossings(Self::pattern(), &mapping, plane),
+                ..Dart::new(mapping, left, right)
+            })
         })
     }
 
@@ -130,19 +173,22 @@ impl Constellation for Dart {
         self.mapping
     }
 
-    fn force_bars(&self, plane: &mut FiveFold) -> bool {
-        if self.left.as_ref().unwrap().seq2().is_none() {
-            plane.force_point(
-                self.left.as_ref().unwrap().point(),
-                self.left.as_ref().unwrap().seq1().as_ref().unwrap(),
-            )
-        } else if self.right.as_ref().unwrap().seq2().is_none() {
-            plane.force_point(
-                self.right.as_ref().unwrap().point(),
-                self.right.as_ref().unwrap().seq1().as_ref().unwrap(),
-            )
+    fn force_bars(&self, plane: &mut FiveFold) -> Option<Forcing> {
+        // darts found in a plane have at least one optional point, but imported ones have none
+        let unforced = |point: &Option<IntersectionPoint>| {
+            let point = point.as_ref().filter(|point| point.seq2().is_none())?;
+            Some((point.point(), point.seq1()?))
+        };
+        if let Some((point, ms)) = unforced(&self.left) {
+            plane
+                .force_point(point, &ms)
+                .map(|forced| Forcing::new(ForcingSource::DartLeft, self.mapping, forced))
+        } else if let Some((point, ms)) = unforced(&self.right) {
+            plane
+                .force_point(point, &ms)
+                .map(|forced| Forcing::new(ForcingSource::DartRight, self.mapping, forced))
         } else {
-            false
+            None
         }
     }
 }
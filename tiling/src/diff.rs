@@ -0,0 +1,146 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use euclid::default::Box2D;
+use rustc_hash::FxHashSet;
+
+use super::fivefold::FiveFold;
+use super::identity::{dart_key, kite_key};
+use super::tiling::{MatchList, Tiling};
+
+/// How one set of tiles differs from another, matching tiles by shape and placement.
+#[derive(Debug, Clone, Default)]
+pub struct TilingDiff {
+    /// The tiles only in the newer set.
+    pub added: MatchList,
+    /// The tiles only in the older set.
+    pub removed: MatchList,
+    /// The tiles in both, as the newer set has them.
+    pub unchanged: MatchList,
+}
+
+impl TilingDiff {
+    /// Whether the sets have the same tiles.
+    pub fn is_empty(&self) -> bool {
+        self.added.kites.is_empty()
+            && self.added.darts.is_empty()
+            && self.removed.kites.is_empty()
+            && self.removed.darts.is_empty()
+    }
+}
+
+impl MatchList {
+    /// The tiles added and removed going from these tiles to `other`.
+    pub fn diff(&self, other: &MatchList) -> TilingDiff {
+        let mut diff = TilingDiff::default();
+
+        let kites = self.kites.iter().map(kite_key).collect::<FxHashSet<_>>();
+        let darts = self.darts.iter().map(dart_key).collect::<FxHashSet<_>>();
+        for kite in other.kites.iter() {
+            match kites.contains(&kite_key(kite)) {
+                true => diff.unchanged.kites.push(*kite),
+                false => diff.added.kites.push(*kite),
+            }
+        }
+        for dart in other.darts.iter() {
+            match darts.contains(&dart_key(dart)) {
+                true => diff.unchanged.darts.push(dart.clone()),
+                false => diff.added.darts.push(dart.clone()),
+            }
+        }
+
+        let kites = other.kites.iter().map(kite_key).collect::<FxHashSet<_>>();
+        let darts = other.darts.iter().map(dart_key).collect::<FxHashSet<_>>();
+        (diff.removed.kites).extend(
+            (self.kites.iter())
+                .filter(|kite| !kites.contains(&kite_key(kite)))
+                .copied(),
+        );
+        (diff.removed.darts).extend(
+            (self.darts.iter())
+                .filter(|dart| !darts.contains(&dart_key(dart)))
+                .cloned(),
+        );
+
+        diff
+    }
+}
+
+impl FiveFold {
+    /// The tiles added and removed within `bounds` going from this plane to `other`, such as
+    /// before and after choosing a bar.
+    pub fn diff(&self, other: &FiveFold, bounds: Box2D<f64>) -> TilingDiff {
+        let before = Tiling::new(self.clone(), bounds).compute_area();
+        let after = Tiling::new(other.clone(), bounds).compute_area();
+        before.diff(&after)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::{Point2D, Transform2D};
+
+    use super::*;
+    use crate::constellation::Constellation;
+    use crate::dart::Dart;
+    use crate::fixtures::sun_patch;
+
+    #[test]
+    fn diff() {
+        let tiles = sun_patch(2);
+        let same = tiles.diff(&tiles);
+        assert!(same.is_empty());
+        assert_eq!(same.unchanged.kites.len(), tiles.kites.len());
+        assert_eq!(same.unchanged.darts.len(), tiles.darts.len());
+
+        let mut changed = tiles.clone();
+        let kite = changed.kites.remove(0);
+        let dart = Dart::new(
+            Transform2D::translation(100f64, 0f64).then(&kite.mapping()),
+            None,
+            None,
+        );
+        changed.darts.push(dart.clone());
+        let diff = tiles.diff(&changed);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.removed.kites.len(), 1);
+        assert_eq!(kite_key(&diff.removed.kites[0]), kite_key(&kite));
+        assert!(diff.removed.darts.is_empty());
+        assert_eq!(diff.added.darts.len(), 1);
+        assert_eq!(dart_key(&diff.added.darts[0]), dart_key(&dart));
+        assert!(diff.added.kites.is_empty());
+        assert_eq!(diff.unchanged.kites.len(), tiles.kites.len() - 1);
+
+        // a different plane keeps some tiles and changes others
+        let bounds = Box2D::new(Point2D::new(-6f64, -6f64), Point2D::new(6f64, 6f64));
+        let king = FiveFold::king_configuration();
+        let queen = FiveFold::queen_configuration();
+        assert!(king.diff(&king, bounds).is_empty());
+        let diff = king.diff(&queen, bounds);
+        let after = Tiling::new(queen, bounds).compute_area();
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.added.kites.len() + diff.unchanged.kites.len(),
+            after.kites.len()
+        );
+        assert_eq!(
+            diff.added.darts.len() + diff.unchanged.darts.len(),
+            after.darts.len()
+        );
+    }
+}
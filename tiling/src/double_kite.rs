@@ -23,9 +23,9 @@ use lazy_static::lazy_static;
 
 use crate::musical_sequence::BarBound;
 
-use super::constellation::{map_optional, test_required, Constellation};
+use super::constellation::{map_optional, test_required, Constellation, Forcing, ForcingSource};
 use super::fivefold::{intersection_point, FiveFold};
-use super::intersection_point::IntersectionPoint;
+use super::intersection_point::{fifth_turns, IntersectionPoint};
 use std::borrow::Borrow;
 
 lazy_static! {
@@ -58,6 +58,19 @@ impl DoubleKite {
     fn new(mapping: Transform2D<f64>) -> Self {
         Self { mapping }
     }
+
+    /// Whether the point to force lies where the plane can no longer have a bar.
+    pub(crate) fn contradicts(&self, plane: &FiveFold) -> bool {
+        map_optional(&FORCE, &self.mapping, plane, 1)
+            .filter(|point| point.seq2().is_none())
+            .is_some_and(|point| {
+                (point.seq1()).is_some_and(|ms| plane.contradicts(point.point(), &ms))
+            })
+    }
+
+    pub(crate) fn rotated(&self, turns: usize) -> DoubleKite {
+        DoubleKite::new(self.mapping.then(&fifth_turns(turns)))
+    }
 }
 
 impl Constellation for DoubleKite {
@@ -66,7 +79,7 @@ impl Constellation for DoubleKite {
     }
 
     fn key_pair() -> &'static [IntersectionPoint; 2] {
-        &*KEY_PAIR
+        &KEY_PAIR
     }
 
     fn pattern() -> &'static [IntersectionPoint] {
@@ -90,12 +103,14 @@ impl Constellation for DoubleKite {
         self.mapping
     }
 
-    fn force_bars(&self, plane: &mut FiveFold) -> bool {
-        let new = map_optional(&*FORCE, &self.mapping, plane, 1).unwrap();
+    fn force_bars(&self, plane: &mut FiveFold) -> Option<Forcing> {
+        let new = map_optional(&FORCE, &self.mapping, plane, 1).unwrap();
 
         if new.seq2().is_none() {
-            return plane.force_point(new.borrow().point(), new.seq1().as_ref().unwrap());
+            return plane
+                .force_point(new.borrow().point(), new.seq1().as_ref().unwrap())
+                .map(|forced| Forcing::new(ForcingSource::DoubleKite, self.mapping, forced));
         }
-        false
+        None
     }
 }
@@ -18,14 +18,17 @@
 
 use std::borrow::{Borrow, BorrowMut};
 use std::collections::BTreeSet;
+use std::error::Error;
 use std::f64::consts::{PI, TAU};
+use std::fmt::{Display, Formatter};
 
 use arrayvec::ArrayVec;
-use euclid::default::{Box2D, Point2D};
+use euclid::default::{Box2D, Point2D, Vector2D};
 use itertools::Itertools;
 
 use crate::musical_sequence::BarBound;
 
+use super::ammann::{clip, clip_strip, AmmannBar, UndeterminedRegion};
 use super::constants::*;
 use super::intersection_point::IntersectionPoint;
 use super::musical_sequence::BarNumber;
@@ -34,13 +37,64 @@ use rustc_hash::FxHashMap;
 
 const N: usize = 5;
 
+// the sequences with bars forced since the intersection points were last updated, by rotation
+#[derive(Debug, Copy, Clone)]
+enum Forced {
+    Nothing,
+    One(f64),
+    Several,
+}
+
 #[derive(Debug, Clone)]
 pub struct FiveFold {
     cache: FxHashMap<(u64, BarNumber, u64, BarNumber), IntersectionPoint>,
     sequences: ArrayVec<MusicalSequence, N>,
-    last_forced: Option<f64>,
+    last_forced: Forced,
+}
+
+/// The forcing state of a plane's sequences, to roll back to later.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    sequences: ArrayVec<MusicalSequence, N>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ChoiceError {
+    /// There are only five sequences.
+    Sequence(usize),
+    /// The bar is already forced the other way.
+    Conflict(BarNumber),
+}
+
+/// The rotations and reflections taking a plane's tiling to itself, about some point.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SymmetryGroup {
+    /// Only the given number of rotations, counting the identity.
+    Cyclic(usize),
+    /// The given number of rotations, and as many reflections.
+    Dihedral(usize),
+}
+
+impl Display for SymmetryGroup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymmetryGroup::Cyclic(n) => write!(f, "C{}", n),
+            SymmetryGroup::Dihedral(n) => write!(f, "D{}", n),
+        }
+    }
 }
 
+impl Display for ChoiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChoiceError::Sequence(index) => write!(f, "no sequence with index {}", index),
+            ChoiceError::Conflict(bar) => write!(f, "bar {} is already forced otherwise", bar),
+        }
+    }
+}
+
+impl Error for ChoiceError {}
+
 /// expect some bars :)
 fn expected_intersections(bounds: &Box2D<f64>) -> usize {
     let avg_bar = (1. * short::<f64>() + golden_ratio::<f64>() * long::<f64>())
@@ -104,9 +158,11 @@ fn distance_along(p: Point2D<f64>, ms: &MusicalSequence) -> f64 {
 
     let theta = (p.y - center.y).atan2(p.x - center.x).rem_euclid(TAU);
 
-    ((theta - ms.rotation()).abs() > epsilon::<f64>())
-        .then(|| -distance)
-        .unwrap_or(distance)
+    if (theta - ms.rotation()).abs() > epsilon::<f64>() {
+        -distance
+    } else {
+        distance
+    }
 }
 
 pub(crate) fn bar_num(p: Point2D<f64>, ms: &MusicalSequence) -> BarNumber {
@@ -162,7 +218,8 @@ fn forced_bars<'a>(
     bars(area, ms, true)
 }
 
-pub(crate) fn intersection_point(
+/// The point where a bar of one sequence crosses a bar of another, as used to describe patterns.
+pub fn intersection_point(
     a: &MusicalSequence,
     a_bar: BarNumber,
     b: &MusicalSequence,
@@ -287,9 +344,7 @@ impl FiveFold {
         let bar = ms.get_bar(distance);
         let bar_dist = ms.get_bar_distance(bar);
 
-        ((distance - bar_dist).abs() <= epsilon::<f64>())
-            .then(|| ms.is_forced(bar))
-            .unwrap_or(false)
+        (distance - bar_dist).abs() <= epsilon::<f64>() && ms.is_forced(bar)
     }
 
     pub(crate) fn is_forced(&self, p: Point2D<f64>, ms: &MusicalSequence) -> bool {
@@ -312,13 +367,17 @@ impl FiveFold {
         })
     }
 
+    pub(crate) fn cache_size(&self) -> usize {
+        self.cache.len()
+    }
+
     pub(crate) fn update_intersection_points(&mut self, bounds: &Box2D<f64>) {
         let expected = expected_intersections(bounds);
         if self.cache.capacity() < expected {
             self.cache.reserve(expected - self.cache.capacity())
         }
 
-        if let Some(forced) = self.last_forced.take() {
+        if let Forced::One(forced) = std::mem::replace(&mut self.last_forced, Forced::Nothing) {
             for (a, a_bar, b, b_bar) in self
                 .sequences
                 .iter()
@@ -375,7 +434,203 @@ impl FiveFold {
         intermediate
     }
 
-    pub(crate) fn sequences(&self) -> &[MusicalSequence] {
+    /// Lists the bars of every sequence which cross the bounds, forced or not, clipped to them.
+    pub fn bars_in(&self, bounds: &Box2D<f64>) -> Vec<AmmannBar> {
+        let mut found = Vec::new();
+
+        for (sequence, ms) in self.sequences.iter().enumerate() {
+            // bars run perpendicular to their sequence
+            let direction = Vector2D::new(-ms.rotation().sin(), ms.rotation().cos());
+
+            let mut numbers = forced_bars(bounds, ms)
+                .map(|bar| (bar, true))
+                .chain(bars(bounds, ms, false).map(|bar| (bar, false)))
+                .collect::<Vec<_>>();
+            numbers.sort_unstable();
+
+            found.extend(numbers.into_iter().filter_map(|(bar, forced)| {
+                let (start, end) = clip(bar_to_point(ms, bar), direction, bounds)?;
+                Some(AmmannBar {
+                    sequence,
+                    bar,
+                    forced,
+                    start,
+                    end,
+                })
+            }));
+        }
+
+        found
+    }
+
+    pub(crate) fn undetermined_in(&self, bounds: &Box2D<f64>) -> Vec<UndeterminedRegion> {
+        let bars = self.bars_in(bounds);
+        let mut regions = Vec::new();
+
+        for (sequence, group) in &bars.iter().group_by(|bar| bar.sequence) {
+            let ms = &self.sequences[sequence];
+            let center = Point2D::new(ms.center_x(), ms.center_y());
+            let axis = Vector2D::new(ms.rotation().cos(), ms.rotation().sin());
+
+            for (_, run) in &group
+                .filter(|bar| !bar.forced)
+                .map(|bar| bar.bar)
+                // consecutive bars share a key
+                .enumerate()
+                .group_by(|&(i, bar)| bar - i as BarNumber)
+            {
+                let run = run.map(|(_, bar)| bar).collect::<Vec<_>>();
+                let (first, last) = (run[0], run[run.len() - 1]);
+
+                let (near, far) = run
+                    .iter()
+                    .flat_map(|&bar| ms.get_bar_distances(bar))
+                    .minmax()
+                    .into_option()
+                    .unwrap();
+                let polygon = clip_strip(bounds, |p| (p - center).dot(axis), near, far);
+                if polygon.len() >= 3 {
+                    regions.push(UndeterminedRegion {
+                        sequence,
+                        bars: first..=last,
+                        polygon,
+                    });
+                }
+            }
+        }
+
+        regions
+    }
+
+    /// Decides an unforced bar of one of the sequences. Returns whether the bar was newly forced;
+    /// choosing a bar the way it is already forced changes nothing.
+    pub fn choose_bar(
+        &mut self,
+        sequence: usize,
+        bar: BarNumber,
+        bound: BarBound,
+    ) -> Result<bool, ChoiceError> {
+        let ms = self
+            .sequences
+            .get_mut(sequence)
+            .ok_or(ChoiceError::Sequence(sequence))?;
+
+        if ms.is_forced(bar) {
+            // where the choice would put the bar had nothing been forced yet
+            let mut unforced = MusicalSequence::default();
+            unforced.force(bar, bound);
+            if (unforced.get_bar_distance(bar) - ms.get_bar_distance(bar)).abs() < epsilon::<f64>()
+            {
+                Ok(false)
+            } else {
+                Err(ChoiceError::Conflict(bar))
+            }
+        } else {
+            ms.force(bar, bound);
+            // the new intersections could belong to any sequence pending an update, so rescan them
+            // all on the next one
+            self.last_forced = Forced::Several;
+            Ok(true)
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            sequences: self.sequences.clone(),
+        }
+    }
+
+    /// Restores the forcing state of a snapshot taken of this plane, forgetting the intersections
+    /// of any bars forced since.
+    pub fn rollback(&mut self, snapshot: &Snapshot) {
+        self.sequences = snapshot.sequences.clone();
+
+        let sequences = &self.sequences;
+        let forced = |rotation: u64, bar: BarNumber| {
+            sequences
+                .iter()
+                .any(|ms| ms.rotation().to_bits() == rotation && ms.is_forced(bar))
+        };
+        self.cache
+            .retain(|&(a, a_bar, b, b_bar), _| forced(a, a_bar) && forced(b, b_bar));
+        self.last_forced = Forced::Several;
+    }
+
+    /// The plane reflected in the x axis if `reflected`, then turned `turns` tenths of a turn about
+    /// the origin. Turning an odd number of tenths takes each sequence onto the axis opposite
+    /// another, and so reverses it.
+    pub(crate) fn transformed(&self, turns: usize, reflected: bool) -> FiveFold {
+        let mut transformed = FiveFold::default();
+        for (i, ms) in self.sequences.iter().enumerate() {
+            let i = if reflected { N - i } else { i };
+            let j = (i + 3 * turns) % N;
+            let rotation = transformed.sequences[j].rotation();
+            transformed.sequences[j] = ms.carried(rotation, turns % 2 == 1);
+        }
+        transformed
+    }
+
+    // the translation taking the plane's bars onto those of the other, if it has the same forcings
+    fn translation_to(&self, other: &FiveFold) -> Option<Vector2D<f64>> {
+        let mut differences = ArrayVec::<_, N>::new();
+        for (ms, other) in self.sequences.iter().zip(other.sequences.iter()) {
+            let (sin, cos) = ms.rotation().sin_cos();
+            differences.push((ms.shift_to(other)?, Vector2D::new(cos, sin)));
+        }
+
+        // the axes of the sequences form a tight frame, so this is the least squares fit
+        let translation = differences
+            .iter()
+            .fold(Vector2D::zero(), |sum, &(difference, axis)| {
+                sum + axis * difference
+            })
+            * (2f64 / N as f64);
+        differences
+            .iter()
+            .all(|&(difference, axis)| (translation.dot(axis) - difference).abs() < 1e-9)
+            .then_some(translation)
+    }
+
+    /// Whether the planes produce the same tiling up to rotation, reflection and translation, as
+    /// far as their forcings decide it, however they number their bars.
+    pub fn congruent(&self, other: &FiveFold) -> bool {
+        (0..2 * N)
+            .cartesian_product([false, true])
+            .any(|(turns, reflected)| {
+                (self.transformed(turns, reflected))
+                    .translation_to(other)
+                    .is_some()
+            })
+    }
+
+    /// The symmetries of the plane's tiling, in the same sense as [`FiveFold::congruent`].
+    pub fn symmetry_group(&self) -> SymmetryGroup {
+        let count = |reflected| {
+            (0..2 * N)
+                .filter(|&turns| {
+                    (self.transformed(turns, reflected))
+                        .translation_to(self)
+                        .is_some()
+                })
+                .count()
+        };
+
+        match count(true) {
+            0 => SymmetryGroup::Cyclic(count(false)),
+            _ => SymmetryGroup::Dihedral(count(false)),
+        }
+    }
+
+    /// Whether turning the plane a fifth of a turn about the origin leaves it the same, taking each
+    /// sequence to the next.
+    pub fn is_rotationally_symmetric(&self) -> bool {
+        let first = self.sequences[0].state();
+        self.sequences.iter().skip(1).all(|ms| {
+            (ms.state().iter().zip(first.iter())).all(|(a, b)| (a - b).abs() < epsilon::<f64>())
+        })
+    }
+
+    pub fn sequences(&self) -> &[MusicalSequence] {
         self.sequences.borrow()
     }
 
@@ -383,22 +638,41 @@ impl FiveFold {
         self.sequences.borrow_mut()
     }
 
-    pub(crate) fn force_point(&mut self, p: Point2D<f64>, ms: &MusicalSequence) -> bool {
-        assert!(self.last_forced.is_none(), "Naughty!");
+    /// Whether the bars of the sequence are forced such that none can pass through the point.
+    pub(crate) fn contradicts(&self, p: Point2D<f64>, ms: &MusicalSequence) -> bool {
+        let distance = distance_along(nearest_point(p, ms), ms);
+        self.sequences
+            .iter()
+            .find(|sequence| (ms.rotation() - sequence.rotation()).abs() < epsilon::<f64>())
+            .is_some_and(|sequence| !sequence.fits(distance))
+    }
+
+    /// Forces the bar of the sequence nearest the point, returning the index of the sequence, the
+    /// bar and its bound if it wasn't already forced. Forcing bars of several sequences between
+    /// computations is allowed, but the next one then rescans every intersection.
+    pub fn force_point(
+        &mut self,
+        p: Point2D<f64>,
+        ms: &MusicalSequence,
+    ) -> Option<(usize, BarNumber, BarBound)> {
         let along = nearest_point(p, ms);
         let distance = distance_along(along, ms);
 
-        let ms = self
+        let (index, ms) = self
             .sequences
             .iter_mut()
-            .find(|sequence| (ms.rotation() - sequence.rotation()).abs() < epsilon::<f64>())
+            .enumerate()
+            .find(|(_, sequence)| (ms.rotation() - sequence.rotation()).abs() < epsilon::<f64>())
             .unwrap();
-        if ms.force_at_distance(distance) {
-            self.last_forced.replace(ms.rotation());
-            true
-        } else {
-            false
-        }
+        let (bar, bound) = ms.force_at_distance(distance)?;
+        self.last_forced = match self.last_forced {
+            Forced::Nothing => Forced::One(ms.rotation()),
+            Forced::One(rotation) if (rotation - ms.rotation()).abs() < epsilon::<f64>() => {
+                Forced::One(rotation)
+            }
+            _ => Forced::Several,
+        };
+        Some((index, bar, bound))
     }
 }
 
@@ -409,14 +683,20 @@ impl Default for FiveFold {
             sequences: (0..N)
                 .map(|i| MusicalSequence::new_with_coords(0f64, 0f64, (i as f64 * TAU) / N as f64))
                 .collect(),
-            last_forced: None,
+            last_forced: Forced::Nothing,
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use euclid::default::Transform2D;
+    use euclid::Angle;
+
+    use crate::constellation::Constellation;
+    use crate::identity::TileKey;
     use crate::musical_sequence::BarBound;
+    use crate::Tiling;
 
     use super::*;
 
@@ -453,4 +733,180 @@ mod test {
             .zip(actual_intersections.iter())
             .for_each(|(expected, actual)| assert_eq!(expected, actual.point()));
     }
+
+    #[test]
+    fn choose_bar() {
+        let mut plane = FiveFold::king_configuration();
+        let bounds = Box2D::new(Point2D::new(-5f64, -5f64), Point2D::new(5f64, 5f64));
+        plane.update_intersection_points(&bounds);
+        let before = plane.intersection_points(&bounds).len();
+
+        let open = plane
+            .bars_in(&bounds)
+            .into_iter()
+            .find(|bar| !bar.forced)
+            .unwrap();
+        assert_eq!(
+            plane.choose_bar(open.sequence, open.bar, BarBound::Shorter),
+            Ok(true)
+        );
+        assert_eq!(
+            plane.choose_bar(open.sequence, open.bar, BarBound::Shorter),
+            Ok(false)
+        );
+        assert_eq!(
+            plane.choose_bar(open.sequence, open.bar, BarBound::Longer),
+            Err(ChoiceError::Conflict(open.bar))
+        );
+        assert_eq!(
+            plane.choose_bar(5, 0, BarBound::Longer),
+            Err(ChoiceError::Sequence(5))
+        );
+
+        // the chosen bar now crosses the other forced bars
+        plane.update_intersection_points(&bounds);
+        let points = plane.intersection_points(&bounds);
+        assert!(points.len() > before);
+        let rotation = plane.sequences()[open.sequence].rotation();
+        assert!(points.iter().any(|point| {
+            [(point.seq1(), point.bar1()), (point.seq2(), point.bar2())]
+                .iter()
+                .any(|(ms, bar)| {
+                    *bar == open.bar
+                        && ms.is_some_and(|ms| (ms.rotation() - rotation).abs() < epsilon::<f64>())
+                })
+        }));
+    }
+
+    #[test]
+    fn bars_in() {
+        let mut plane = FiveFold::king_configuration();
+        let bounds = Box2D::new(Point2D::new(-5f64, -5f64), Point2D::new(5f64, 5f64));
+        let bars = plane.bars_in(&bounds);
+
+        for (sequence, ms) in plane.sequences().iter().enumerate() {
+            let numbers = bars
+                .iter()
+                .filter(|bar| bar.sequence == sequence)
+                .map(|bar| bar.bar)
+                .collect::<Vec<_>>();
+            assert!(!numbers.is_empty());
+            assert!(numbers.windows(2).all(|pair| pair[1] == pair[0] + 1));
+
+            for bar in bars.iter().filter(|bar| bar.sequence == sequence) {
+                assert_eq!(bar.forced, ms.is_forced(bar.bar));
+                let along = bar.end - bar.start;
+                assert!(
+                    along
+                        .dot(Vector2D::new(ms.rotation().cos(), ms.rotation().sin()))
+                        .abs()
+                        < 1e-9
+                );
+                assert!(bounds.inflate(1e-9, 1e-9).contains(bar.start));
+                assert!(bounds.inflate(1e-9, 1e-9).contains(bar.end));
+            }
+        }
+
+        // every intersection point lies on two of the forced bars
+        let on = |point: Point2D<f64>, bar: &AmmannBar| {
+            let along = (bar.end - bar.start).normalize();
+            let offset = point - bar.start;
+            (offset.x * along.y - offset.y * along.x).abs() < 1e-6
+        };
+        plane.update_intersection_points(&bounds);
+        for point in plane.intersection_points(&bounds) {
+            let count = bars
+                .iter()
+                .filter(|bar| bar.forced && on(point.point(), bar))
+                .count();
+            assert_eq!(count, 2);
+        }
+    }
+
+    #[test]
+    fn symmetry_group() {
+        let groups = [
+            (FiveFold::ace_configuration(), SymmetryGroup::Dihedral(1)),
+            (FiveFold::deuce_configuration(), SymmetryGroup::Dihedral(1)),
+            (FiveFold::sun_configuration(), SymmetryGroup::Dihedral(5)),
+            (FiveFold::star_configuration(), SymmetryGroup::Dihedral(5)),
+            (FiveFold::jack_configuration(), SymmetryGroup::Dihedral(1)),
+            (FiveFold::queen_configuration(), SymmetryGroup::Dihedral(1)),
+            (FiveFold::king_configuration(), SymmetryGroup::Dihedral(1)),
+        ];
+        for (plane, group) in groups.iter() {
+            assert_eq!(plane.symmetry_group(), *group);
+        }
+        assert_eq!(SymmetryGroup::Dihedral(5).to_string(), "D5");
+    }
+
+    #[test]
+    fn congruent() {
+        let king = FiveFold::king_configuration();
+        assert!(king.congruent(&king));
+        assert!(!king.congruent(&FiveFold::queen_configuration()));
+        assert!(!FiveFold::sun_configuration().congruent(&FiveFold::star_configuration()));
+
+        let mut moved = king.transformed(3, true);
+        for ms in moved.sequences_mut() {
+            let (sin, cos) = ms.rotation().sin_cos();
+            ms.set_zeroeth(ms.state()[0] + 2f64 * cos - 3f64 * sin);
+        }
+        assert!(king.congruent(&moved));
+        assert!(moved.congruent(&king));
+
+        // the transformed planes really do produce the transformed tiles
+        let bounds = Box2D::new(Point2D::new(-8f64, -8f64), Point2D::new(8f64, 8f64));
+        let keys = |plane: FiveFold, transform: Transform2D<f64>| {
+            let tiles = Tiling::new(plane, bounds).compute_area();
+            let mut keys = (tiles.kites.iter().map(|kite| kite.mapping()))
+                .chain(tiles.darts.iter().map(|dart| dart.mapping()))
+                .map(|mapping| mapping.then(&transform))
+                .filter(|mapping| {
+                    mapping
+                        .transform_point(Point2D::origin())
+                        .to_vector()
+                        .length()
+                        < 5f64
+                })
+                .map(|mapping| TileKey::from(&mapping))
+                .collect::<Vec<_>>();
+            keys.sort();
+            keys
+        };
+        let tenth = Transform2D::rotation(Angle::degrees(36f64));
+        let mirror = Transform2D::scale(1f64, -1f64);
+        assert!(!keys(king.clone(), Transform2D::identity()).is_empty());
+        assert_eq!(
+            keys(king.clone(), tenth),
+            keys(king.transformed(1, false), Transform2D::identity())
+        );
+        assert_eq!(
+            keys(FiveFold::ace_configuration(), mirror.then(&tenth)),
+            keys(
+                FiveFold::ace_configuration().transformed(1, true),
+                Transform2D::identity()
+            )
+        );
+    }
+
+    #[test]
+    fn rollback() {
+        let mut plane = FiveFold::king_configuration();
+        let bounds = Box2D::new(Point2D::new(-5f64, -5f64), Point2D::new(5f64, 5f64));
+        plane.update_intersection_points(&bounds);
+        let before = plane.intersection_points(&bounds).len();
+        let snapshot = plane.snapshot();
+
+        for bar in plane.bars_in(&bounds).into_iter().filter(|bar| !bar.forced) {
+            let _ = plane.choose_bar(bar.sequence, bar.bar, BarBound::Longer);
+        }
+        plane.update_intersection_points(&bounds);
+        assert!(plane.intersection_points(&bounds).len() > before);
+        assert!(plane.bars_in(&bounds).iter().all(|bar| bar.forced));
+
+        plane.rollback(&snapshot);
+        plane.update_intersection_points(&bounds);
+        assert_eq!(plane.intersection_points(&bounds).len(), before);
+    }
 }
@@ -0,0 +1,94 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use euclid::default::Transform2D;
+use euclid::Angle;
+use rustc_hash::FxHashSet;
+
+use super::constants::golden_ratio;
+use super::constellation::Constellation;
+use super::dart::Dart;
+use super::hierarchy::{shrink, wing, wing_kite};
+use super::identity::dart_key;
+use super::kite::Kite;
+use super::tiling::MatchList;
+
+/// Splits every tile into the tiles of the level below. A kite becomes the two kites at its wings
+/// and halves of the two darts along its long edges; a dart becomes the kite at its nose and halves
+/// of the two darts along its long edges.
+pub(crate) fn deflate(tiles: &MatchList) -> MatchList {
+    let mut deflated = MatchList::default();
+    for kite in tiles.kites.iter() {
+        let mapping = kite.mapping();
+        for first in [true, false] {
+            deflated
+                .kites
+                .push(Kite::new(wing_kite(first).then(&mapping)));
+        }
+        for rotation in [36., -36.] {
+            let dart = shrink().then_rotate(Angle::degrees(rotation));
+            deflated
+                .darts
+                .push(Dart::new(dart.then(&mapping), None, None));
+        }
+    }
+    for dart in tiles.darts.iter() {
+        let mapping = dart.mapping();
+        deflated.kites.push(Kite::new(shrink().then(&mapping)));
+        for (rotation, sign) in [(216., 1.), (144., -1.)] {
+            let dart = shrink()
+                .then_rotate(Angle::degrees(rotation))
+                .then_translate(wing(sign));
+            deflated
+                .darts
+                .push(Dart::new(dart.then(&mapping), None, None));
+        }
+    }
+
+    // each half of a dart produces the whole dart
+    let mut seen = FxHashSet::default();
+    deflated.darts.retain(|dart| seen.insert(dart_key(dart)));
+    deflated
+}
+
+/// The five kites around a sun vertex at the origin.
+pub(crate) fn sun() -> MatchList {
+    MatchList {
+        kites: (0..5)
+            .map(|i| Kite::new(Transform2D::rotation(Angle::degrees(72. * i as f64))))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// The sun deflated `levels` times, scaled back up to unit edges.
+pub(crate) fn sun_patch(levels: i32) -> MatchList {
+    let mut tiles = sun();
+    for _ in 0..levels {
+        tiles = deflate(&tiles);
+    }
+    let scale = golden_ratio::<f64>().powi(levels);
+    MatchList {
+        kites: (tiles.kites.iter())
+            .map(|kite| Kite::new(kite.mapping().then_scale(scale, scale)))
+            .collect(),
+        darts: (tiles.darts.iter())
+            .map(|dart| Dart::new(dart.mapping().then_scale(scale, scale), None, None))
+            .collect(),
+    }
+}
@@ -0,0 +1,385 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use arrayvec::ArrayVec;
+use euclid::default::{Point2D, Transform2D, Vector2D};
+use euclid::Angle;
+use rustc_hash::FxHashMap;
+
+use super::constants::golden_ratio;
+use super::constellation::Constellation;
+use super::dart::Dart;
+use super::identity::{PointKey, TileIndex, TileKey, TileLookup};
+use super::kite::Kite;
+use super::shape::Shape;
+use super::tiling::MatchList;
+
+type Parents = ArrayVec<TileIndex, 2>;
+
+// kites and darts both keep their short edges either side of the third point of their paths
+const SHORT_EDGES: [(usize, usize); 2] = [(1, 2), (2, 3)];
+
+/// The supertiles a computed tiling composes into, level by level. Every kite belongs to exactly
+/// one supertile of the level above, while every dart is split along its axis between two.
+///
+/// Tiles missing a neighbour across one of their short edges, whether at the edge of the computed
+/// area or beside a gap left by unforced bars, usually can't be placed and have no parents. The
+/// gaps are filled first by [`Tiling::hierarchy`](crate::Tiling::hierarchy).
+#[derive(Debug, Clone)]
+pub struct Hierarchy {
+    levels: Vec<MatchList>,
+    parents: Vec<LevelParents>,
+}
+
+#[derive(Debug, Clone)]
+struct LevelParents {
+    kites: Vec<Parents>,
+    darts: Vec<Parents>,
+}
+
+impl LevelParents {
+    fn get(&self, tile: TileIndex) -> &[TileIndex] {
+        match tile {
+            TileIndex::Kite(i) => self.kites.get(i),
+            TileIndex::Dart(i) => self.darts.get(i),
+        }
+        .map_or(&[], |parents| parents.as_slice())
+    }
+}
+
+fn edge_key(path: &[Point2D<f64>; 4], (a, b): (usize, usize)) -> (PointKey, PointKey) {
+    let (a, b) = (PointKey::from(path[a]), PointKey::from(path[b]));
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+pub(crate) fn shrink() -> Transform2D<f64> {
+    Transform2D::scale(1. / golden_ratio::<f64>(), 1. / golden_ratio::<f64>())
+}
+
+pub(crate) fn wing(sign: f64) -> Vector2D<f64> {
+    Vector2D::from_angle_and_length(Angle::degrees(sign * 36.), golden_ratio::<f64>())
+}
+
+// the kite at the wing on the side of its own first short edge, and the kite at the other wing
+pub(crate) fn wing_kite(first: bool) -> Transform2D<f64> {
+    let (rotation, sign) = if first { (252., 1.) } else { (108., -1.) };
+    shrink()
+        .then_rotate(Angle::degrees(rotation))
+        .then_translate(wing(sign))
+}
+
+fn compose(tiles: &MatchList) -> (MatchList, LevelParents) {
+    let lookup = TileLookup::new(tiles);
+
+    // duplicates of a tile defer to the first copy of it
+    let kites = tiles
+        .kites
+        .iter()
+        .map(|kite| lookup.kite(&kite.mapping()).unwrap())
+        .collect::<Vec<_>>();
+    let darts = tiles
+        .darts
+        .iter()
+        .map(|dart| lookup.dart(&dart.mapping()).unwrap())
+        .collect::<Vec<_>>();
+
+    let mut edges = FxHashMap::<_, Parents>::default();
+    let paths = kites
+        .iter()
+        .enumerate()
+        .filter(|&(i, &canonical)| i == canonical)
+        .map(|(i, _)| (TileIndex::Kite(i), tiles.kites[i].path()))
+        .chain(
+            darts
+                .iter()
+                .enumerate()
+                .filter(|&(i, &canonical)| i == canonical)
+                .map(|(i, _)| (TileIndex::Dart(i), tiles.darts[i].path())),
+        )
+        .collect::<Vec<_>>();
+    for (tile, path) in paths.iter() {
+        for edge in SHORT_EDGES {
+            // a third tile on one edge means the input overlaps itself; ignore it
+            let _ = edges
+                .entry(edge_key(path, edge))
+                .or_default()
+                .try_push(*tile);
+        }
+    }
+    let across = |tile: TileIndex, path: &[Point2D<f64>; 4]| {
+        SHORT_EDGES.map(|edge| {
+            edges
+                .get(&edge_key(path, edge))
+                .and_then(|sharing| sharing.iter().copied().find(|&other| other != tile))
+        })
+    };
+
+    let mut composed = MatchList::default();
+    let mut supertiles = FxHashMap::default();
+    let mut parents = LevelParents {
+        kites: vec![Parents::new(); tiles.kites.len()],
+        darts: vec![Parents::new(); tiles.darts.len()],
+    };
+
+    // every kite decides its own supertile: one flanked by darts on both short edges is the body
+    // of a super-dart, while one sharing a short edge with another kite sits at the wing of a
+    // super-kite
+    for &(tile, path) in paths.iter() {
+        let i = match tile {
+            TileIndex::Kite(i) => i,
+            TileIndex::Dart(_) => continue,
+        };
+        let mapping = tiles.kites[i].mapping();
+        let (is_kite, supertile) = match across(tile, &path) {
+            [Some(TileIndex::Dart(_)), Some(TileIndex::Dart(_))] => {
+                (false, shrink().inverse().unwrap().then(&mapping))
+            }
+            [Some(TileIndex::Kite(_)), other] | [other, Some(TileIndex::Kite(_))]
+                if !matches!(other, Some(TileIndex::Kite(_))) =>
+            {
+                let first = matches!(across(tile, &path)[0], Some(TileIndex::Kite(_)));
+                (true, wing_kite(first).inverse().unwrap().then(&mapping))
+            }
+            _ => continue,
+        };
+
+        let parent = *supertiles
+            .entry((is_kite, TileKey::from(&supertile)))
+            .or_insert_with(|| {
+                if is_kite {
+                    composed.kites.push(Kite::new(supertile));
+                    TileIndex::Kite(composed.kites.len() - 1)
+                } else {
+                    composed.darts.push(Dart::new(supertile, None, None));
+                    TileIndex::Dart(composed.darts.len() - 1)
+                }
+            });
+        parents.kites[i].push(parent);
+    }
+
+    // a dart is split along its axis, each half belonging to the supertile of the kite across its
+    // short edge
+    for &(tile, path) in paths.iter() {
+        if let TileIndex::Dart(i) = tile {
+            for other in across(tile, &path).iter().flatten() {
+                if let &TileIndex::Kite(j) = other {
+                    if let Some(&parent) = parents.kites[j].first() {
+                        if !parents.darts[i].contains(&parent) {
+                            parents.darts[i].push(parent);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for (i, &canonical) in kites.iter().enumerate() {
+        parents.kites[i] = parents.kites[canonical].clone();
+    }
+    for (i, &canonical) in darts.iter().enumerate() {
+        parents.darts[i] = parents.darts[canonical].clone();
+    }
+
+    (composed, parents)
+}
+
+impl Hierarchy {
+    pub fn new(tiles: &MatchList, depth: usize) -> Self {
+        let mut levels: Vec<MatchList> = Vec::with_capacity(depth);
+        let mut parents = Vec::with_capacity(depth);
+
+        while levels.len() < depth {
+            let (composed, level_parents) = compose(levels.last().unwrap_or(tiles));
+            if composed.kites.is_empty() && composed.darts.is_empty() {
+                break;
+            }
+            levels.push(composed);
+            parents.push(level_parents);
+        }
+
+        Self { levels, parents }
+    }
+
+    // rebases the bottom level from `composed`, which the hierarchy was composed from, onto
+    // `tiles`, which are some of its tiles
+    pub(crate) fn reindexed(mut self, composed: &MatchList, tiles: &MatchList) -> Self {
+        let bottom = match self.parents.first_mut() {
+            Some(bottom) => bottom,
+            None => return self,
+        };
+        let lookup = TileLookup::new(composed);
+        let parents = |found: Option<TileIndex>| {
+            found.map_or_else(Parents::new, |tile| {
+                bottom.get(tile).iter().copied().collect()
+            })
+        };
+        *bottom = LevelParents {
+            kites: (tiles.kites.iter())
+                .map(|kite| parents(lookup.kite(&kite.mapping()).map(TileIndex::Kite)))
+                .collect(),
+            darts: (tiles.darts.iter())
+                .map(|dart| parents(lookup.dart(&dart.mapping()).map(TileIndex::Dart)))
+                .collect(),
+        };
+        self
+    }
+
+    /// The number of levels above the original tiling which could be composed.
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The supertiles at `level`, starting from 1 for the first composition.
+    pub fn level(&self, level: usize) -> Option<&MatchList> {
+        level.checked_sub(1).and_then(|i| self.levels.get(i))
+    }
+
+    /// The supertiles at `level + 1` which `tile` of `level` is part of.
+    pub fn parents(&self, level: usize, tile: TileIndex) -> &[TileIndex] {
+        self.parents
+            .get(level)
+            .map_or(&[], |parents| parents.get(tile))
+    }
+
+    /// The supertiles at `level` which contain some part of `tile` from the original tiling.
+    pub fn containing(&self, tile: TileIndex, level: usize) -> Vec<TileIndex> {
+        if level > self.depth() {
+            return Vec::new();
+        }
+
+        let mut current = vec![tile];
+        for parents in self.parents.iter().take(level) {
+            current = current
+                .into_iter()
+                .flat_map(|tile| parents.get(tile).iter().copied())
+                .collect();
+            current.sort_unstable();
+            current.dedup();
+        }
+        current
+    }
+}
+
+impl MatchList {
+    /// Composes these tiles into supertiles, up to `depth` levels or until nothing more composes.
+    pub fn hierarchy(&self, depth: usize) -> Hierarchy {
+        Hierarchy::new(self, depth)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Box2D;
+    use rustc_hash::FxHashSet;
+
+    use super::*;
+    use crate::fixtures::{deflate, sun};
+    use crate::{FiveFold, Tiling};
+
+    fn keys(tiles: &MatchList) -> (FxHashSet<TileKey>, FxHashSet<TileKey>) {
+        (
+            tiles
+                .kites
+                .iter()
+                .map(|kite| TileKey::from(&kite.mapping()))
+                .collect(),
+            tiles
+                .darts
+                .iter()
+                .map(|dart| TileKey::from(&dart.mapping()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn compose_inverts_deflate() {
+        let mut tiles = sun();
+        for _ in 0..4 {
+            let deflated = deflate(&tiles);
+            let (composed, parents) = compose(&deflated);
+            assert_eq!(keys(&composed), keys(&tiles));
+            assert!(parents.kites.iter().all(|parents| parents.len() == 1));
+            assert!(parents.darts.iter().all(|parents| !parents.is_empty()));
+            tiles = deflated;
+        }
+    }
+
+    #[test]
+    fn containing() {
+        let mut tiles = sun();
+        for _ in 0..3 {
+            tiles = deflate(&tiles);
+        }
+
+        let hierarchy = tiles.hierarchy(5);
+        assert_eq!(hierarchy.depth(), 3);
+        assert_eq!(keys(hierarchy.level(3).unwrap()), keys(&sun()));
+        assert!(hierarchy.level(0).is_none());
+
+        let top = hierarchy.level(3).unwrap();
+        for tile in (0..tiles.kites.len())
+            .map(TileIndex::Kite)
+            .chain((0..tiles.darts.len()).map(TileIndex::Dart))
+        {
+            let containing = hierarchy.containing(tile, 3);
+            assert!(!containing.is_empty());
+            assert!(containing.iter().all(|&parent| match parent {
+                TileIndex::Kite(i) => i < top.kites.len(),
+                TileIndex::Dart(i) => i < top.darts.len(),
+            }));
+        }
+        for i in 0..tiles.kites.len() {
+            assert_eq!(hierarchy.containing(TileIndex::Kite(i), 1).len(), 1);
+        }
+        assert!(hierarchy.containing(TileIndex::Kite(0), 4).is_empty());
+    }
+
+    #[test]
+    fn computed() {
+        let bounds = Box2D::new(Point2D::new(-10., -10.), Point2D::new(10., 10.));
+        let inner = bounds.inflate(-3., -3.);
+        for plane in [
+            FiveFold::king_configuration(),
+            FiveFold::star_configuration(),
+        ] {
+            let mut tiling = Tiling::new(plane, bounds);
+            let tiles = tiling.compute_area();
+            let hierarchy = tiling.hierarchy(&tiles, 1);
+            assert_eq!(hierarchy.depth(), 1);
+
+            let inside = |path: &[Point2D<f64>; 4]| path.iter().all(|&point| inner.contains(point));
+            let tiles = (tiles.kites.iter().enumerate())
+                .filter(|(_, kite)| inside(&kite.path()))
+                .map(|(i, _)| TileIndex::Kite(i))
+                .chain(
+                    (tiles.darts.iter().enumerate())
+                        .filter(|(_, dart)| inside(&dart.path()))
+                        .map(|(i, _)| TileIndex::Dart(i)),
+                )
+                .collect::<Vec<_>>();
+            assert!(tiles.len() > 20);
+            for tile in tiles {
+                assert!(!hierarchy.parents(0, tile).is_empty());
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use euclid::default::{Point2D, Transform2D};
+use rustc_hash::FxHashMap;
+
+use super::dart::Dart;
+use super::kite::Kite;
+use super::tiling::MatchList;
+use crate::constellation::Constellation;
+
+// tile placements are exact up to floating point noise, so a micro-unit grid is plenty
+const RESOLUTION: f64 = 1e6;
+
+/// A tile of a [`MatchList`], by its position in the list for its shape.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum TileIndex {
+    Kite(usize),
+    Dart(usize),
+}
+
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub(crate) struct PointKey(i64, i64);
+
+impl From<Point2D<f64>> for PointKey {
+    fn from(point: Point2D<f64>) -> Self {
+        Self(
+            (point.x * RESOLUTION).round() as i64,
+            (point.y * RESOLUTION).round() as i64,
+        )
+    }
+}
+
+/// Where a tile sits, independent of how its mapping was derived: its origin and the end of its
+/// unit axis. Mirror images of the same placement share a key.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub(crate) struct TileKey {
+    origin: PointKey,
+    axis: PointKey,
+}
+
+impl From<&Transform2D<f64>> for TileKey {
+    fn from(mapping: &Transform2D<f64>) -> Self {
+        Self {
+            origin: mapping.transform_point(Point2D::origin()).into(),
+            axis: mapping.transform_point(Point2D::new(1f64, 0f64)).into(),
+        }
+    }
+}
+
+pub(crate) struct TileLookup {
+    kites: FxHashMap<TileKey, usize>,
+    darts: FxHashMap<TileKey, usize>,
+}
+
+impl TileLookup {
+    pub(crate) fn new(tiles: &MatchList) -> Self {
+        let mut kites = FxHashMap::default();
+        let mut darts = FxHashMap::default();
+
+        for (i, kite) in tiles.kites.iter().enumerate() {
+            kites.entry(TileKey::from(&kite.mapping())).or_insert(i);
+        }
+        for (i, dart) in tiles.darts.iter().enumerate() {
+            darts.entry(TileKey::from(&dart.mapping())).or_insert(i);
+        }
+
+        Self { kites, darts }
+    }
+
+    pub(crate) fn kite(&self, mapping: &Transform2D<f64>) -> Option<usize> {
+        self.kites.get(&mapping.into()).copied()
+    }
+
+    pub(crate) fn dart(&self, mapping: &Transform2D<f64>) -> Option<usize> {
+        self.darts.get(&mapping.into()).copied()
+    }
+}
+
+pub(crate) fn kite_key(kite: &Kite) -> TileKey {
+    TileKey::from(&kite.mapping())
+}
+
+pub(crate) fn dart_key(dart: &Dart) -> TileKey {
+    TileKey::from(&dart.mapping())
+}
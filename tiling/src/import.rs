@@ -0,0 +1,515 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use euclid::default::{Point2D, Transform2D, Vector2D};
+use euclid::{vec2, Angle};
+use rustc_hash::FxHashMap;
+
+use super::dart::Dart;
+use super::kite::Kite;
+use super::shape::Shape;
+use super::tiling::MatchList;
+
+// how far a corner may stray from the exact tile, relative to the tile's scale
+const SNAP: f64 = 0.05;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ImportError {
+    /// Malformed input, at the given line of CSV or byte of JSON.
+    Syntax(usize),
+    /// The polygon at the given index has a number of corners other than four.
+    Corners(usize, usize),
+    /// The polygon at the given index is neither a kite nor a dart.
+    Shape(usize),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Syntax(position) => write!(f, "malformed input at {}", position),
+            ImportError::Corners(index, corners) => {
+                write!(f, "polygon {} has {} corners, not 4", index, corners)
+            }
+            ImportError::Shape(index) => {
+                write!(f, "polygon {} is neither a kite nor a dart", index)
+            }
+        }
+    }
+}
+
+impl Error for ImportError {}
+
+// a similarity transform, as the complex numbers `scale` and `offset` in `offset + scale * z`
+fn similarity(scale: Vector2D<f64>, offset: Vector2D<f64>) -> Transform2D<f64> {
+    Transform2D::new(scale.x, scale.y, -scale.y, scale.x, offset.x, offset.y)
+}
+
+/// Fits the scaled, rotated and translated copy of `local` closest to `vertices` in the least
+/// squares sense, where `vertices` may start at any corner and run in either direction.
+pub(crate) fn snap(
+    local: [Point2D<f64>; 4],
+    vertices: &[Point2D<f64>; 4],
+) -> Option<Transform2D<f64>> {
+    let local_mean = (local.iter().map(|q| q.to_vector()).sum::<Vector2D<f64>>()) / 4f64;
+    let mean = (vertices
+        .iter()
+        .map(|p| p.to_vector())
+        .sum::<Vector2D<f64>>())
+        / 4f64;
+    let spread = (local.iter())
+        .map(|q| (q.to_vector() - local_mean).square_length())
+        .sum::<f64>();
+
+    (0..8)
+        .map(|k| {
+            let corner = |i: usize| vertices[if k < 4 { (k + i) % 4 } else { (k - i) % 4 }];
+            // the sum of p * conj(q) over the centred corners
+            let scale = (0..4).fold(Vector2D::zero(), |sum, i| {
+                let (p, q) = (
+                    corner(i).to_vector() - mean,
+                    local[i].to_vector() - local_mean,
+                );
+                sum + vec2(p.x * q.x + p.y * q.y, p.y * q.x - p.x * q.y)
+            }) / spread;
+            let offset = mean - similarity(scale, Vector2D::zero()).transform_vector(local_mean);
+            let mapping = similarity(scale, offset);
+            let error = (0..4)
+                .map(|i| {
+                    mapping
+                        .transform_point(local[i])
+                        .distance_to(corner(i))
+                        .powi(2)
+                })
+                .sum::<f64>();
+
+            ((error / 4f64).sqrt() / scale.length(), mapping)
+        })
+        .filter(|(error, _)| *error < SNAP)
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+        .map(|(_, mapping)| mapping)
+}
+
+enum Json {
+    Number(f64),
+    Array(Vec<(usize, Json)>),
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    // arrays nest at most `depth` deep, which keeps the recursion bounded
+    fn value(&mut self, depth: usize) -> Result<Json, ImportError> {
+        self.skip_whitespace();
+        let rest = &self.text[self.position..];
+        if rest.starts_with('[') {
+            if depth == 0 {
+                return Err(ImportError::Syntax(self.position));
+            }
+            self.position += 1;
+            let mut items = Vec::new();
+            self.skip_whitespace();
+            if self.text[self.position..].starts_with(']') {
+                self.position += 1;
+                return Ok(Json::Array(items));
+            }
+            loop {
+                self.skip_whitespace();
+                let start = self.position;
+                items.push((start, self.value(depth - 1)?));
+                self.skip_whitespace();
+                match self.text[self.position..].chars().next() {
+                    Some(',') => self.position += 1,
+                    Some(']') => {
+                        self.position += 1;
+                        return Ok(Json::Array(items));
+                    }
+                    _ => return Err(ImportError::Syntax(self.position)),
+                }
+            }
+        } else {
+            let length = rest
+                .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                .unwrap_or(rest.len());
+            let number = rest[..length]
+                .parse()
+                .map_err(|_| ImportError::Syntax(self.position))?;
+            self.position += length;
+            Ok(Json::Number(number))
+        }
+    }
+}
+
+fn point((position, value): &(usize, Json)) -> Result<Point2D<f64>, ImportError> {
+    match value {
+        Json::Array(coordinates) => match coordinates.as_slice() {
+            [(_, Json::Number(x)), (_, Json::Number(y))] => Ok(Point2D::new(*x, *y)),
+            _ => Err(ImportError::Syntax(*position)),
+        },
+        Json::Number(_) => Err(ImportError::Syntax(*position)),
+    }
+}
+
+// the scaling about the origin that brings a fitted tile down to unit size
+fn normalising(mapping: &Transform2D<f64>) -> Transform2D<f64> {
+    let scale = mapping.m11.hypot(mapping.m12);
+    Transform2D::scale(1f64 / scale, 1f64 / scale)
+}
+
+impl Kite {
+    /// Snaps a quadrilateral to the nearest kite, then scales it about the origin down to a unit
+    /// kite. The corners may start anywhere and run in either direction, but must be within a few
+    /// percent of an exact kite. The kite comes with the scaling, which maps the quadrilateral
+    /// onto it.
+    pub fn from_vertices(vertices: &[Point2D<f64>; 4]) -> Option<(Kite, Transform2D<f64>)> {
+        let kite = Kite::new(Transform2D::identity()).path();
+        snap(kite, vertices).map(|mapping| {
+            let normalising = normalising(&mapping);
+            (Kite::new(mapping.then(&normalising)), normalising)
+        })
+    }
+}
+
+impl Dart {
+    /// Snaps a quadrilateral to the nearest unit dart, as [`Kite::from_vertices`] does kites.
+    pub fn from_vertices(vertices: &[Point2D<f64>; 4]) -> Option<(Dart, Transform2D<f64>)> {
+        let dart = Dart::new(Transform2D::identity(), None, None).path();
+        snap(dart, vertices).map(|mapping| {
+            let normalising = normalising(&mapping);
+            (
+                Dart::new(mapping.then(&normalising), None, None),
+                normalising,
+            )
+        })
+    }
+}
+
+impl MatchList {
+    /// Rebuilds a tiling from the corners of its tiles, telling kites from darts by their shape.
+    /// A polygon may repeat its first corner at the end.
+    ///
+    /// The tiles are welded back together through their shared corners, starting from the first
+    /// polygon, so they meet exactly even if the corners were rounded on the way in. Everything is
+    /// scaled about the origin so that the first tile has unit short edges, and the tiles come
+    /// with that scaling, which maps the polygons onto them.
+    pub fn from_polygons(
+        polygons: &[Vec<Point2D<f64>>],
+    ) -> Result<(MatchList, Transform2D<f64>), ImportError> {
+        let kite = Kite::new(Transform2D::identity()).path();
+        let dart = Dart::new(Transform2D::identity(), None, None).path();
+
+        let fitted = (polygons.iter().enumerate())
+            .map(|(i, polygon)| {
+                let corners = match polygon.as_slice() {
+                    [rest @ .., last] if rest.first() == Some(last) => rest,
+                    corners => corners,
+                };
+                let corners = <&[Point2D<f64>; 4]>::try_from(corners)
+                    .map_err(|_| ImportError::Corners(i, corners.len()))?;
+
+                (snap(kite, corners).map(|mapping| (kite, mapping)))
+                    .or_else(|| snap(dart, corners).map(|mapping| (dart, mapping)))
+                    .ok_or(ImportError::Shape(i))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (normalising, rotation) = match fitted.first() {
+            Some((_, first)) => (normalising(first), first.m12.atan2(first.m11)),
+            None => return Ok((MatchList::default(), Transform2D::identity())),
+        };
+        // every tile turns a multiple of 36 degrees from the first
+        let step = 36f64.to_radians();
+        let tiles = (fitted.iter())
+            .map(|(local, mapping)| {
+                let mapping = mapping.then(&normalising);
+                let turn = (mapping.m12.atan2(mapping.m11) - rotation) / step;
+                let exact = Transform2D::rotation(Angle::radians(rotation + turn.round() * step));
+                (
+                    *local,
+                    exact,
+                    local.map(|corner| mapping.transform_point(corner)),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // corners of different tiles within a fifth of an edge are taken to be the same vertex
+        let mut grid = FxHashMap::<(i64, i64), Vec<usize>>::default();
+        let mut vertices = Vec::<Point2D<f64>>::new();
+        let mut vertex_tiles = Vec::<Vec<(usize, usize)>>::new();
+        let mut corner_vertices = Vec::with_capacity(tiles.len());
+        for (i, (_, _, corners)) in tiles.iter().enumerate() {
+            let mut ids = [0; 4];
+            for (j, corner) in corners.iter().enumerate() {
+                let cell = (
+                    (corner.x * 2f64).floor() as i64,
+                    (corner.y * 2f64).floor() as i64,
+                );
+                let nearby = (-1..=1)
+                    .flat_map(|dx| (-1..=1).map(move |dy| (cell.0 + dx, cell.1 + dy)))
+                    .flat_map(|cell| grid.get(&cell).into_iter().flatten())
+                    .find(|&&id| vertices[id].distance_to(*corner) < 0.2)
+                    .copied();
+                ids[j] = nearby.unwrap_or_else(|| {
+                    vertices.push(*corner);
+                    vertex_tiles.push(Vec::new());
+                    grid.entry(cell).or_default().push(vertices.len() - 1);
+                    vertices.len() - 1
+                });
+                vertex_tiles[ids[j]].push((i, j));
+            }
+            corner_vertices.push(ids);
+        }
+
+        let mut exact = vec![None; vertices.len()];
+        let mut placed = vec![None; tiles.len()];
+        for seed in 0..tiles.len() {
+            if placed[seed].is_some() {
+                continue;
+            }
+            exact[corner_vertices[seed][0]].get_or_insert(tiles[seed].2[0]);
+
+            let mut queue = vec![(seed, 0)];
+            while let Some((i, j)) = queue.pop() {
+                if placed[i].is_some() {
+                    continue;
+                }
+                let (local, rotation, _) = &tiles[i];
+                let at = exact[corner_vertices[i][j]].unwrap();
+                let mapping = rotation.then_translate(at - rotation.transform_point(local[j]));
+                placed[i] = Some(mapping);
+
+                for (corner, &id) in local.iter().zip(corner_vertices[i].iter()) {
+                    exact[id].get_or_insert(mapping.transform_point(*corner));
+                    queue.extend(
+                        vertex_tiles[id]
+                            .iter()
+                            .filter(|(k, _)| placed[*k].is_none()),
+                    );
+                }
+            }
+        }
+
+        let mut list = MatchList::default();
+        for ((local, _, _), mapping) in tiles.iter().zip(placed) {
+            let mapping = mapping.unwrap();
+            if *local == kite {
+                list.kites.push(Kite::new(mapping));
+            } else {
+                list.darts.push(Dart::new(mapping, None, None));
+            }
+        }
+
+        Ok((list, normalising))
+    }
+
+    /// Reads polygons from CSV, one per line as `x0,y0,x1,y1,...`. Blank lines and lines starting
+    /// with `#` are skipped. The tiles come with their scaling, as from
+    /// [`MatchList::from_polygons`].
+    pub fn from_csv(text: &str) -> Result<(MatchList, Transform2D<f64>), ImportError> {
+        let polygons = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !(line.trim().is_empty() || line.trim_start().starts_with('#')))
+            .map(|(i, line)| {
+                let values = line
+                    .split(',')
+                    .map(|value| value.trim().parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| ImportError::Syntax(i + 1))?;
+                if values.len() % 2 != 0 {
+                    return Err(ImportError::Syntax(i + 1));
+                }
+                Ok(values
+                    .chunks(2)
+                    .map(|xy| Point2D::new(xy[0], xy[1]))
+                    .collect())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        MatchList::from_polygons(&polygons)
+    }
+
+    /// Reads polygons from JSON, as an array of polygons each an array of `[x, y]` corners. The
+    /// tiles come with their scaling, as from [`MatchList::from_polygons`].
+    pub fn from_json(text: &str) -> Result<(MatchList, Transform2D<f64>), ImportError> {
+        let mut parser = Parser { text, position: 0 };
+        // polygons of corners of coordinates
+        let value = parser.value(3)?;
+        parser.skip_whitespace();
+        if parser.position != text.len() {
+            return Err(ImportError::Syntax(parser.position));
+        }
+
+        let polygons = match value {
+            Json::Array(polygons) => polygons,
+            Json::Number(_) => return Err(ImportError::Syntax(0)),
+        };
+        let polygons = polygons
+            .iter()
+            .map(|(position, polygon)| match polygon {
+                Json::Array(corners) => corners.iter().map(point).collect(),
+                Json::Number(_) => Err(ImportError::Syntax(*position)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        MatchList::from_polygons(&polygons)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constellation::Constellation;
+    use crate::fixtures::sun_patch;
+    use crate::identity::{dart_key, kite_key, TileKey};
+
+    // every corner but those of the first tile nudged off the exact tile, in no particular pattern
+    fn polygons(tiles: &MatchList) -> Vec<Vec<Point2D<f64>>> {
+        let nudge = |i: usize| 1e-3 * ((i * 7919 % 13) as f64 - 6.) / 6.;
+        (tiles.kites.iter().map(|kite| kite.path()))
+            .chain(tiles.darts.iter().map(|dart| dart.path()))
+            .enumerate()
+            .map(|(i, path)| {
+                let nudge = |j| if i == 0 { 0f64 } else { nudge(j) };
+                (path.iter().enumerate())
+                    .map(|(j, corner)| *corner + euclid::vec2(nudge(i + j), nudge(i * j + 1)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn keys(tiles: &MatchList) -> (Vec<TileKey>, Vec<TileKey>) {
+        let mut kites = tiles.kites.iter().map(kite_key).collect::<Vec<_>>();
+        let mut darts = tiles.darts.iter().map(dart_key).collect::<Vec<_>>();
+        kites.sort();
+        darts.sort();
+        (kites, darts)
+    }
+
+    #[test]
+    fn round_trip() {
+        let tiles = sun_patch(3);
+        let polygons = polygons(&tiles);
+
+        let csv = polygons
+            .iter()
+            .map(|polygon| {
+                (polygon.iter())
+                    .map(|corner| format!("{},{}", corner.x, corner.y))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (csv, _) = MatchList::from_csv(&format!("# x0,y0,...\n\n{}\n", csv)).unwrap();
+        assert_eq!(keys(&csv), keys(&tiles));
+
+        // closed rings, with the corners running clockwise
+        let json = polygons
+            .iter()
+            .map(|polygon| {
+                let corners = (polygon.iter().rev().chain(polygon.last()))
+                    .map(|corner| format!("[{}, {}]", corner.x, corner.y))
+                    .collect::<Vec<_>>();
+                format!("[{}]", corners.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join(",\n ");
+        let (json, _) = MatchList::from_json(&format!("[{}]", json)).unwrap();
+        assert_eq!(keys(&json), keys(&tiles));
+        assert_eq!(json.verify(), Ok(()));
+
+        // any scale, brought down to unit size by a scaling that undoes to give back the input
+        let matches =
+            |path: [Point2D<f64>; 4], back: Transform2D<f64>, corners: &[Point2D<f64>]| {
+                (path.iter()).all(|&corner| {
+                    (corners.iter())
+                        .any(|other| back.transform_point(corner).distance_to(*other) < 1e-9)
+                })
+            };
+        let scaled = (polygons.iter())
+            .map(|polygon| polygon.iter().map(|&corner| corner * 10.).collect())
+            .collect::<Vec<Vec<_>>>();
+        let (big, normalising) = MatchList::from_polygons(&scaled).unwrap();
+        assert_eq!(keys(&big), keys(&tiles));
+        let back = normalising.inverse().unwrap();
+        assert!(matches(big.kites[0].path(), back, &scaled[0]));
+
+        let kite = tiles.kites[1].path().map(|corner| corner * 10.);
+        let (snapped, normalising) = Kite::from_vertices(&kite).unwrap();
+        assert!((snapped.mapping().m11.hypot(snapped.mapping().m12) - 1.).abs() < 1e-9);
+        assert!(matches(
+            snapped.path(),
+            normalising.inverse().unwrap(),
+            &kite
+        ));
+        let dart = tiles.darts[0].path().map(|corner| corner * 0.1);
+        let (snapped, normalising) = Dart::from_vertices(&dart).unwrap();
+        assert!((snapped.mapping().m11.hypot(snapped.mapping().m12) - 1.).abs() < 1e-9);
+        assert!(matches(
+            snapped.path(),
+            normalising.inverse().unwrap(),
+            &dart
+        ));
+        assert!(Dart::from_vertices(&tiles.kites[0].path()).is_none());
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            MatchList::from_csv("0,0,1,0\n0,x").unwrap_err(),
+            ImportError::Syntax(2)
+        );
+        assert_eq!(
+            MatchList::from_csv("0,0,1,0,1,1").unwrap_err(),
+            ImportError::Corners(0, 3)
+        );
+        assert_eq!(
+            MatchList::from_csv("0,0,1,0,1,1,0,1").unwrap_err(),
+            ImportError::Shape(0)
+        );
+        assert_eq!(
+            MatchList::from_json("[[[0, 0], [1]]]").unwrap_err(),
+            ImportError::Syntax(10)
+        );
+        assert_eq!(
+            MatchList::from_json("[[]] x").unwrap_err(),
+            ImportError::Syntax(5)
+        );
+        assert!(MatchList::from_json("[]").unwrap().0.kites.is_empty());
+        assert_eq!(
+            MatchList::from_json("[[[[0]]]]").unwrap_err(),
+            ImportError::Syntax(3)
+        );
+        assert_eq!(
+            MatchList::from_json(&"[".repeat(200000)).unwrap_err(),
+            ImportError::Syntax(3)
+        );
+    }
+}
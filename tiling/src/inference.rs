@@ -0,0 +1,318 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::error::Error;
+use std::f64::consts::TAU;
+use std::fmt::{Display, Formatter};
+
+use euclid::default::{Transform2D, Vector2D};
+use euclid::Angle;
+
+use super::constants::{golden_ratio, scale};
+use super::constellation::Constellation;
+use super::dart::Dart;
+use super::fivefold::FiveFold;
+use super::identity::TileIndex;
+use super::kite::Kite;
+use super::musical_sequence::{BarNumber, MusicalSequence};
+use super::tiling::MatchList;
+
+const N: usize = 5;
+
+// how many bar numberings either side of the nearest to try for a sequence's first bar
+const SEARCH: BarNumber = 100;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InferenceError {
+    /// The tile is not turned a multiple of 36 degrees from the plane's axes.
+    Orientation(TileIndex),
+    /// The bars of the sequence seen in the patch are not spaced like a musical sequence.
+    Sequence(usize),
+}
+
+impl Display for InferenceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InferenceError::Orientation(tile) => {
+                write!(f, "{:?} is not aligned with the plane", tile)
+            }
+            InferenceError::Sequence(index) => {
+                write!(
+                    f,
+                    "bars of sequence {} do not form a musical sequence",
+                    index
+                )
+            }
+        }
+    }
+}
+
+impl Error for InferenceError {}
+
+fn axis(index: usize) -> Vector2D<f64> {
+    let (sin, cos) = (index as f64 * TAU / N as f64).sin_cos();
+    Vector2D::new(cos, sin)
+}
+
+// the distances along each axis of the bars a tile's pattern lies on
+fn bars_of<C: Constellation>(
+    mapping: &Transform2D<f64>,
+    distances: &mut [Vec<f64>; N],
+) -> Option<()> {
+    for point in C::pattern() {
+        let at = mapping.transform_point(point.point()).to_vector();
+        for sequence in [point.seq1(), point.seq2()].iter().flatten() {
+            let (sin, cos) = sequence.rotation().sin_cos();
+            let direction = mapping
+                .transform_vector(Vector2D::new(cos, sin))
+                .normalize();
+            let index = (0..N).find(|&i| (direction.dot(axis(i)).abs() - 1f64).abs() < 1e-6)?;
+            distances[index].push(at.dot(axis(index)));
+        }
+    }
+    Some(())
+}
+
+// the turn about the origin that brings the first tile onto the nearest multiple of 36 degrees
+fn onto_axes(tiles: &MatchList) -> Transform2D<f64> {
+    let first = (tiles.kites.first().map(|kite| kite.mapping()))
+        .or_else(|| tiles.darts.first().map(|dart| dart.mapping()));
+    let rotation = first.map_or(0f64, |first| first.m12.atan2(first.m11));
+    let step = 36f64.to_radians();
+    Transform2D::rotation(Angle::radians((rotation / step).round() * step - rotation))
+}
+
+fn observed_bars(
+    tiles: &MatchList,
+    turn: &Transform2D<f64>,
+) -> Result<[Vec<f64>; N], InferenceError> {
+    let mut distances: [Vec<f64>; N] = Default::default();
+    for (i, kite) in tiles.kites.iter().enumerate() {
+        bars_of::<Kite>(&kite.mapping().then(turn), &mut distances)
+            .ok_or(InferenceError::Orientation(TileIndex::Kite(i)))?;
+    }
+    for (i, dart) in tiles.darts.iter().enumerate() {
+        bars_of::<Dart>(&dart.mapping().then(turn), &mut distances)
+            .ok_or(InferenceError::Orientation(TileIndex::Dart(i)))?;
+    }
+    for distances in distances.iter_mut() {
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        distances.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+    }
+    Ok(distances)
+}
+
+// splits a distance between two bars into its short and long gaps, as a number of bars and a number
+// of points
+fn offset(distance: f64) -> Option<(BarNumber, BarNumber)> {
+    let gaps = distance / scale::<f64>();
+    (0..=(gaps / golden_ratio::<f64>()).ceil() as BarNumber).find_map(|longs| {
+        let shorts = (gaps - longs as f64 * golden_ratio::<f64>()).round();
+        ((gaps - shorts - longs as f64 * golden_ratio::<f64>()).abs() < 1e-6 && shorts >= 0f64)
+            .then(|| (shorts as BarNumber + longs, shorts as BarNumber + 2 * longs))
+    })
+}
+
+impl FiveFold {
+    /// Reconstructs a plane which generates the given tiles, placing each sequence's bars where the
+    /// tiles' patterns put them and forcing them accordingly. Bars no tile sees are left unforced,
+    /// so computing a larger area extends the patch to a full tiling.
+    ///
+    /// The tiles may lie at any angle, so long as each is turned a multiple of 36 degrees from the
+    /// others. They are turned about the origin onto the plane's axes first, and the plane comes
+    /// with that turn, which maps the given tiles onto those it generates.
+    pub fn from_tiles(tiles: &MatchList) -> Result<(FiveFold, Transform2D<f64>), InferenceError> {
+        let mut plane = FiveFold::default();
+        let turn = onto_axes(tiles);
+
+        for (index, (distances, sequence)) in observed_bars(tiles, &turn)?
+            .iter()
+            .zip(plane.sequences_mut())
+            .enumerate()
+        {
+            let first = match distances.first() {
+                Some(&first) => first,
+                None => continue,
+            };
+            let offsets = distances
+                .iter()
+                .map(|distance| offset(distance - first))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(InferenceError::Sequence(index))?;
+
+            // number the bars so that bar zero lands as near the origin as it can, without the
+            // intercepts the bars allow wrapping around the ends of the unforced range and so
+            // being cut short. Planes inferred from translated tiles then differ only in how they
+            // number their bars.
+            let estimate = sequence.get_bar(first);
+            let numberings = (0..SEARCH)
+                .flat_map(|i| [estimate - i, estimate + i + 1])
+                .filter_map(|bar| {
+                    (sequence.with_offsets(bar, offsets.iter().copied()))
+                        .map(|sequence| (bar, sequence))
+                })
+                .collect::<Vec<_>>();
+            let width = |sequence: &MusicalSequence| sequence.state()[2] - sequence.state()[1];
+            let widest = (numberings.iter())
+                .map(|(_, sequence)| width(sequence))
+                .fold(0f64, f64::max);
+            let mut found = numberings
+                .into_iter()
+                .find(|(_, sequence)| width(sequence) > widest - 1e-9)
+                .ok_or(InferenceError::Sequence(index))?;
+            found
+                .1
+                .set_zeroeth(first - found.1.get_bar_distance(found.0));
+            *sequence = found.1;
+        }
+
+        Ok((plane, turn))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::{Box2D, Point2D};
+    use rustc_hash::FxHashSet;
+
+    use super::*;
+    use crate::identity::{dart_key, kite_key};
+    use crate::shape::Shape;
+    use crate::Tiling;
+
+    fn contains(tiles: &MatchList, patch: &MatchList) -> bool {
+        let kites = tiles.kites.iter().map(kite_key).collect::<FxHashSet<_>>();
+        let darts = tiles.darts.iter().map(dart_key).collect::<FxHashSet<_>>();
+        patch
+            .kites
+            .iter()
+            .all(|kite| kites.contains(&kite_key(kite)))
+            && patch
+                .darts
+                .iter()
+                .all(|dart| darts.contains(&dart_key(dart)))
+    }
+
+    fn turned(tiles: &MatchList, turn: &Transform2D<f64>) -> MatchList {
+        MatchList {
+            kites: (tiles.kites.iter())
+                .map(|kite| Kite::new(kite.mapping().then(turn)))
+                .collect(),
+            darts: (tiles.darts.iter())
+                .map(|dart| Dart::new(dart.mapping().then(turn), None, None))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn from_tiles() {
+        let bounds = Box2D::new(Point2D::new(-10., -10.), Point2D::new(10., 10.));
+        let tiles = Tiling::new(FiveFold::king_configuration(), bounds).compute_area();
+
+        let (plane, turn) = FiveFold::from_tiles(&tiles).unwrap();
+        assert!(turn.approx_eq(&Transform2D::identity()));
+        let again = Tiling::new(plane, bounds).compute_area();
+        assert!(contains(&again, &tiles));
+
+        // a patch from the middle grows back into the tiles around it
+        let middle = Box2D::new(Point2D::new(-4., -4.), Point2D::new(4., 4.));
+        let patch = MatchList {
+            kites: (tiles.kites.iter())
+                .filter(|kite| kite.path().iter().all(|&corner| middle.contains(corner)))
+                .copied()
+                .collect(),
+            darts: (tiles.darts.iter())
+                .filter(|dart| dart.path().iter().all(|&corner| middle.contains(corner)))
+                .cloned()
+                .collect(),
+        };
+        let (plane, _) = FiveFold::from_tiles(&patch).unwrap();
+        let grown = Tiling::new(plane, bounds).compute_area();
+        assert!(contains(&grown, &patch));
+        assert!(grown.kites.len() > patch.kites.len());
+
+        // tiles may lie at any angle, but only together
+        let askew = MatchList {
+            kites: vec![
+                Kite::new(Transform2D::identity()),
+                Kite::new(Transform2D::rotation(Angle::degrees(10.))),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            FiveFold::from_tiles(&askew).unwrap_err(),
+            InferenceError::Orientation(TileIndex::Kite(1))
+        );
+    }
+
+    #[test]
+    fn from_turned_polygons() {
+        let bounds = Box2D::new(Point2D::new(-6., -6.), Point2D::new(6., 6.));
+        let tiles = Tiling::new(FiveFold::king_configuration(), bounds).compute_area();
+
+        let turn = Transform2D::rotation(Angle::degrees(10.));
+        let polygons = (tiles.kites.iter().map(|kite| kite.path().to_vec()))
+            .chain(tiles.darts.iter().map(|dart| dart.path().to_vec()))
+            .map(|path| {
+                path.iter()
+                    .map(|&corner| turn.transform_point(corner))
+                    .collect()
+            })
+            .collect::<Vec<_>>();
+        // the import keeps the polygons' frame, and the plane turns it back onto its axes
+        let (imported, _) = MatchList::from_polygons(&polygons).unwrap();
+        assert!(contains(&turned(&tiles, &turn), &imported));
+
+        let (plane, back) = FiveFold::from_tiles(&imported).unwrap();
+        assert!(back.approx_eq(&turn.inverse().unwrap()));
+        let again = Tiling::new(plane, bounds).compute_area();
+        assert!(contains(&again, &turned(&imported, &back)));
+    }
+
+    #[test]
+    fn translated_congruent() {
+        let bounds = Box2D::new(Point2D::new(-8., -8.), Point2D::new(8., 8.));
+        let tiles = Tiling::new(FiveFold::king_configuration(), bounds).compute_area();
+        let (plane, _) = FiveFold::from_tiles(&tiles).unwrap();
+
+        let shifts = [
+            Vector2D::new(-3., 1.7),
+            Vector2D::new(1.5, -4.),
+            Vector2D::new(20., -13.),
+        ];
+        for shift in shifts {
+            let polygons = (tiles.kites.iter().map(|kite| kite.path().to_vec()))
+                .chain(tiles.darts.iter().map(|dart| dart.path().to_vec()))
+                .map(|path| path.iter().map(|&corner| corner + shift).collect())
+                .collect::<Vec<_>>();
+            let (moved, _) =
+                FiveFold::from_tiles(&MatchList::from_polygons(&polygons).unwrap().0).unwrap();
+
+            // bar zero stays near the origin, so the bars are numbered differently
+            let intercepts = |plane: &FiveFold| {
+                (plane.sequences().iter())
+                    .map(|ms| ms.state()[1])
+                    .collect::<Vec<_>>()
+            };
+            assert_ne!(intercepts(&plane), intercepts(&moved));
+            assert!(plane.congruent(&moved));
+            assert!(moved.congruent(&plane));
+        }
+        assert!(!plane.congruent(&FiveFold::from_tiles(&MatchList::default()).unwrap().0));
+    }
+}
@@ -19,7 +19,8 @@
 use std::cmp::{max_by, Ordering};
 use std::collections::BTreeSet;
 
-use euclid::default::Point2D;
+use euclid::default::{Point2D, Transform2D};
+use euclid::Angle;
 
 use super::constants::*;
 use super::musical_sequence::BarNumber;
@@ -27,6 +28,37 @@ use super::musical_sequence::MusicalSequence;
 use std::f64::consts::TAU;
 use std::fmt::{Display, Formatter};
 
+/// A rotation about the origin by a number of fifths of a turn, which takes each sequence of a
+/// plane to the one after it.
+pub(crate) fn fifth_turns(turns: usize) -> Transform2D<f64> {
+    Transform2D::rotation(Angle::radians(turns as f64 * TAU / 5f64))
+}
+
+fn sequence_index(ms: MusicalSequence) -> usize {
+    (ms.rotation() / (TAU / 5f64)).round() as usize % 5
+}
+
+/// Where a bar of one sequence crosses a bar of another, by the index of each sequence in its
+/// plane.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Crossing {
+    pub point: Point2D<f64>,
+    pub sequences: [usize; 2],
+    pub bars: [BarNumber; 2],
+}
+
+impl Crossing {
+    /// The crossing of the same bars in the sequences `turns` on, in a plane symmetric under
+    /// [`fifth_turns`].
+    pub(crate) fn rotated(&self, turns: usize) -> Crossing {
+        Crossing {
+            point: fifth_turns(turns).transform_point(self.point),
+            sequences: self.sequences.map(|index| (index + turns) % 5),
+            bars: self.bars,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct IntersectionPoint {
     pub(crate) data: Data,
@@ -69,19 +101,19 @@ impl IntersectionPoint {
         self.data.point.y
     }
     #[inline(always)]
-    pub(crate) fn seq1(&self) -> Option<MusicalSequence> {
+    pub fn seq1(&self) -> Option<MusicalSequence> {
         self.data.seq1
     }
     #[inline(always)]
-    pub(crate) fn bar1(&self) -> BarNumber {
+    pub fn bar1(&self) -> BarNumber {
         self.data.bar1
     }
     #[inline(always)]
-    pub(crate) fn seq2(&self) -> Option<MusicalSequence> {
+    pub fn seq2(&self) -> Option<MusicalSequence> {
         self.data.seq2
     }
     #[inline(always)]
-    pub(crate) fn bar2(&self) -> BarNumber {
+    pub fn bar2(&self) -> BarNumber {
         self.data.bar2
     }
     #[inline(always)]
@@ -92,6 +124,33 @@ impl IntersectionPoint {
     pub(crate) fn box_theta(&self) -> f64 {
         self.data.box_theta
     }
+    /// The crossing this point describes, if it lies on two bars.
+    pub fn crossing(&self) -> Option<Crossing> {
+        Some(Crossing {
+            point: self.point(),
+            sequences: [sequence_index(self.seq1()?), sequence_index(self.seq2()?)],
+            bars: [self.bar1(), self.bar2()],
+        })
+    }
+
+    /// The point on the same bars of the sequences `turns` on, in a plane symmetric under
+    /// [`fifth_turns`] with the given sequences.
+    pub(crate) fn rotated(&self, turns: usize, sequences: &[MusicalSequence]) -> Self {
+        let point = fifth_turns(turns).transform_point(self.point());
+        let turn = |ms: MusicalSequence| sequences[(sequence_index(ms) + turns) % 5];
+
+        match (self.seq1(), self.seq2()) {
+            (Some(a), Some(b)) => {
+                Self::new_with_point(&turn(a), self.bar1(), &turn(b), self.bar2(), point)
+            }
+            (seq1, _) => {
+                let mut rotated = Self::incomplete(point);
+                rotated.data.seq1 = seq1.map(turn);
+                rotated
+            }
+        }
+    }
+
     pub(crate) fn incomplete(point: Point2D<f64>) -> Self {
         Self {
             data: Data {
@@ -218,34 +277,32 @@ impl OrderingExt for Ordering {
 
 impl PartialOrd for IntersectionPoint {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(
-            self.box_layer()
-                .partial_cmp(&other.box_layer())
-                .unwrap()
-                .and_then(|| self.box_theta().partial_cmp(&other.box_theta()).unwrap())
-                .and_then(|| {
-                    self.seq1()
-                        .unwrap()
-                        .rotation()
-                        .partial_cmp(&other.seq1().unwrap().rotation())
-                        .unwrap()
-                })
-                .and_then(|| {
-                    self.seq2()
-                        .unwrap()
-                        .rotation()
-                        .partial_cmp(&other.seq2().unwrap().rotation())
-                        .unwrap()
-                })
-                .and_then(|| self.bar1().cmp(&other.bar1()))
-                .and_then(|| self.bar2().cmp(&other.bar2())),
-        )
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for IntersectionPoint {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        self.box_layer()
+            .partial_cmp(&other.box_layer())
+            .unwrap()
+            .and_then(|| self.box_theta().partial_cmp(&other.box_theta()).unwrap())
+            .and_then(|| {
+                self.seq1()
+                    .unwrap()
+                    .rotation()
+                    .partial_cmp(&other.seq1().unwrap().rotation())
+                    .unwrap()
+            })
+            .and_then(|| {
+                self.seq2()
+                    .unwrap()
+                    .rotation()
+                    .partial_cmp(&other.seq2().unwrap().rotation())
+                    .unwrap()
+            })
+            .and_then(|| self.bar1().cmp(&other.bar1()))
+            .and_then(|| self.bar2().cmp(&other.bar2()))
     }
 }
 
@@ -25,9 +25,9 @@ use euclid::default::Transform2D;
 use lazy_static::lazy_static;
 
 use super::constants::*;
-use super::constellation::{test_required, Constellation};
+use super::constellation::{crossings, test_required, Constellation, Forcing};
 use super::fivefold::{intersection_point, FiveFold};
-use super::intersection_point::IntersectionPoint;
+use super::intersection_point::{fifth_turns, Crossing, IntersectionPoint};
 use super::shape::{Shape, Triangle};
 
 lazy_static! {
@@ -69,11 +69,27 @@ lazy_static! {
 #[derive(Debug, Copy, Clone)]
 pub struct Kite {
     mapping: Transform2D<f64>,
+    crossings: Option<[Crossing; 3]>,
 }
 
 impl Kite {
-    fn new(mapping: Transform2D<f64>) -> Self {
-        Self { mapping }
+    pub(crate) fn new(mapping: Transform2D<f64>) -> Self {
+        Self {
+            mapping,
+            crossings: None,
+        }
+    }
+
+    /// The crossings of the bars which produced the kite, if it was found in a plane.
+    pub fn crossings(&self) -> Option<[Crossing; 3]> {
+        self.crossings
+    }
+
+    pub(crate) fn rotated(&self, turns: usize) -> Kite {
+        Kite {
+            mapping: self.mapping.then(&fifth_turns(turns)),
+            crossings: (self.crossings).map(|crossings| crossings.map(|c| c.rotated(turns))),
+        }
     }
 }
 
@@ -83,7 +99,7 @@ impl Constellation for Kite {
     }
 
     fn key_pair() -> &'static [IntersectionPoint; 2] {
-        &*KEY_PAIR
+        &KEY_PAIR
     }
 
     fn pattern() -> &'static [IntersectionPoint] {
@@ -97,15 +113,18 @@ impl Constellation for Kite {
     ) -> Option<Self> {
         let mapping = test_required(points, plane, pair, Self::key_pair(), Self::pattern());
 
-        mapping.map(Kite::new)
+        mapping.map(|mapping| Kite {
+            crossings: crossings(Self::pattern(), &mapping, plane),
+            ..Kite::new(mapping)
+        })
     }
 
     fn mapping(&self) -> Transform2D<f64> {
         self.mapping
     }
 
-    fn force_bars(&self, _: &mut FiveFold) -> bool {
-        false
+    fn force_bars(&self, _: &mut FiveFold) -> Option<Forcing> {
+        None
     }
 }
 
@@ -16,23 +16,54 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-pub use constellation::Constellation;
+pub use ammann::{AmmannBar, UndeterminedRegion};
+pub use compute::{Computation, ComputeError, ComputeOptions, ComputeStats, Progress};
+pub use constellation::{
+    map_optional, test_required, Constellation, Forcing, ForcingSource, PointGraph,
+};
 pub use dart::Dart;
+pub use diff::TilingDiff;
 pub use double_kite::DoubleKite;
-pub use fivefold::FiveFold;
+pub use fivefold::{intersection_point, ChoiceError, FiveFold, Snapshot, SymmetryGroup};
+pub use hierarchy::Hierarchy;
+pub use identity::TileIndex;
+pub use import::ImportError;
+pub use inference::InferenceError;
+pub use intersection_point::{Crossing, IntersectionPoint};
 pub use kite::Kite;
-pub use musical_sequence::MusicalSequence;
-pub use shape::Shape;
+pub use musical_sequence::{Bar, BarBound, BarNumber, MusicalSequence, WordError};
+pub use patch::{Occurrence, PatchClass};
+pub use pentagonal::{Boat, Diamond, Pentagon, PentagonalList, Pentagram};
+pub use phason::{Frame, PhasonError};
+pub use rhombus::{RhombusList, ThickRhombus, ThinRhombus};
+pub use robinson::{Chirality, RobinsonTriangle, TriangleKind};
+pub use shape::{Shape, Triangle};
 pub use tiling::MatchList;
 pub use tiling::Tiling;
+pub use verify::Violation;
 
+mod ammann;
+mod compute;
 pub mod constants;
 mod constellation;
 mod dart;
+mod diff;
 mod double_kite;
 mod fivefold;
+#[cfg(test)]
+mod fixtures;
+mod hierarchy;
+mod identity;
+mod import;
+mod inference;
 mod intersection_point;
 mod kite;
 mod musical_sequence;
+mod patch;
+mod pentagonal;
+mod phason;
+mod rhombus;
+mod robinson;
 mod shape;
 mod tiling;
+mod verify;
@@ -17,6 +17,8 @@
  */
 
 use std::cmp::Ordering::Equal;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::ops::Range;
 
 use itertools::*;
@@ -32,6 +34,38 @@ pub enum BarBound {
     Shorter,
 }
 
+/// A bar as currently placed in its sequence. The positions of unforced bars, and the bounds of the
+/// bars next to them, are only a guess until they are forced.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bar {
+    pub number: BarNumber,
+    pub distance: f64,
+    /// The length of the gap between the bar and its neighbour towards the zeroeth bar.
+    pub bound: BarBound,
+    pub forced: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WordError {
+    /// A symbol other than `L` or `S`, at the given index of the word.
+    Symbol(usize, char),
+    /// No bars consistent with the sequence's forcings produce the word.
+    Illegal,
+}
+
+impl Display for WordError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WordError::Symbol(index, symbol) => {
+                write!(f, "unexpected symbol {:?} at index {}", symbol, index)
+            }
+            WordError::Illegal => write!(f, "word is not legal for this sequence"),
+        }
+    }
+}
+
+impl Error for WordError {}
+
 #[derive(Debug, Copy, Clone)]
 pub struct MusicalSequence {
     upper_x: BarNumber,
@@ -120,15 +154,34 @@ impl MusicalSequence {
         self.find_upper_point(bar) == self.find_lower_point(bar)
     }
 
-    pub(crate) fn get_bar_distance(&self, bar: BarNumber) -> f64 {
-        let y = self.find_upper_point(bar);
+    fn distance_at(bar: BarNumber, y: BarNumber) -> f64 {
         let shorts = (2 * bar) - y;
         let longs = y - bar;
 
         scale::<f64>() * (shorts as f64 + golden_ratio::<f64>() * longs as f64)
     }
 
+    pub(crate) fn get_bar_distance(&self, bar: BarNumber) -> f64 {
+        Self::distance_at(bar, self.find_upper_point(bar))
+    }
+
+    /// The distances a bar could still end up at, which are the same once it is forced.
+    pub(crate) fn get_bar_distances(&self, bar: BarNumber) -> [f64; 2] {
+        [
+            Self::distance_at(bar, self.find_lower_point(bar)),
+            Self::distance_at(bar, self.find_upper_point(bar)),
+        ]
+    }
+
+    // finding bars by distance counts points from zero, which only holds for intercepts between
+    // zero and one, so count them from the whole part of the intercept instead
+    fn past_whole_points(&self, distance: f64) -> f64 {
+        let intercept = (self.find_lower_intercept() + self.find_upper_intercept()) / 2f64;
+        distance - Self::distance_at(0, intercept.floor() as BarNumber)
+    }
+
     pub(crate) fn get_bar(&self, distance: f64) -> BarNumber {
+        let distance = self.past_whole_points(distance);
         let shorts = distance / (short::<f64>() + (golden_ratio::<f64>() * long::<f64>()));
         let longs = shorts * golden_ratio::<f64>();
 
@@ -143,8 +196,9 @@ impl MusicalSequence {
         }
     }
 
-    pub(crate) fn force_at_distance(&mut self, distance: f64) -> bool {
-        let scaled = distance / scale::<f64>();
+    // the bar nearest a distance, and the bound which would put it there
+    fn nearest_bar(&self, distance: f64) -> (BarNumber, BarBound) {
+        let scaled = self.past_whole_points(distance) / scale::<f64>();
 
         let shorts = scaled / (2f64 + golden_ratio::<f64>());
         let longs = shorts * golden_ratio::<f64>();
@@ -169,19 +223,31 @@ impl MusicalSequence {
             .unwrap();
 
         let bars = shorts[best_short] + longs[best_long];
+        let bound = if (best_long == 1 && bars > -1) || (best_long == 0 && bars < 0) {
+            BarBound::Longer
+        } else {
+            BarBound::Shorter
+        };
+        (bars, bound)
+    }
 
-        if !self.is_forced(bars) {
-            if (best_long == 1 && bars > -1) || (best_long == 0 && bars < 0) {
-                self.force(bars, BarBound::Longer);
-            } else {
-                self.force(bars, BarBound::Shorter);
-            }
-            true
+    pub(crate) fn force_at_distance(&mut self, distance: f64) -> Option<(BarNumber, BarBound)> {
+        let (bar, bound) = self.nearest_bar(distance);
+
+        if !self.is_forced(bar) {
+            self.force(bar, bound);
+            Some((bar, bound))
         } else {
-            false
+            None
         }
     }
 
+    /// Whether a bar could still lie at the distance, i.e. the nearest bar isn't forced elsewhere.
+    pub(crate) fn fits(&self, distance: f64) -> bool {
+        let (bar, _) = self.nearest_bar(distance);
+        !self.is_forced(bar) || (self.get_bar_distance(bar) - distance).abs() < 1e-6
+    }
+
     pub fn get_bar_forcings<I>(&self, r: I) -> Vec<bool>
     where
         I: Iterator<Item = BarNumber>,
@@ -206,6 +272,159 @@ impl MusicalSequence {
         res
     }
 
+    /// Iterates over the bars in the range, in order.
+    pub fn bars(&self, r: Range<BarNumber>) -> impl Iterator<Item = Bar> + '_ {
+        r.map(move |bar| {
+            let towards_zero = if bar >= 0 { bar - 1 } else { bar + 1 };
+            let gap = (self.find_upper_point(bar) - self.find_upper_point(towards_zero)).abs();
+
+            Bar {
+                number: bar,
+                distance: self.get_bar_distance(bar),
+                bound: if gap == 2 {
+                    BarBound::Longer
+                } else {
+                    BarBound::Shorter
+                },
+                forced: self.is_forced(bar),
+            }
+        })
+    }
+
+    /// The gaps following each bar in the range as a Fibonacci word, `L` for long gaps and `S` for
+    /// short ones.
+    pub fn word(&self, r: Range<BarNumber>) -> String {
+        r.map(|bar| {
+            if self.find_upper_point(bar + 1) - self.find_upper_point(bar) == 2 {
+                'L'
+            } else {
+                'S'
+            }
+        })
+        .collect()
+    }
+
+    /// Copies the sequence with the bars from `first` onwards forced to follow the gaps of `word`,
+    /// as produced by [`MusicalSequence::word`]. Fails if the word is not a factor of any Sturmian
+    /// word the sequence could still become.
+    pub fn with_word(&self, first: BarNumber, word: &str) -> Result<MusicalSequence, WordError> {
+        let gaps = word
+            .chars()
+            .enumerate()
+            .map(|(i, symbol)| match symbol {
+                'L' => Ok(2),
+                'S' => Ok(1),
+                _ => Err(WordError::Symbol(i, symbol)),
+            })
+            .collect::<Result<Vec<BarNumber>, _>>()?;
+
+        let offsets = (0..=gaps.len()).map(|i| (i as BarNumber, gaps[..i].iter().sum()));
+        self.with_offsets(first, offsets).ok_or(WordError::Illegal)
+    }
+
+    /// Copies the sequence with bars forced so that each `(bars, points)` offset from `first` is
+    /// the same number of points along as it is bars along, counting short gaps as one point and
+    /// long gaps as two. Bars in between are left alone.
+    pub(crate) fn with_offsets<I>(&self, first: BarNumber, offsets: I) -> Option<MusicalSequence>
+    where
+        I: Iterator<Item = (BarNumber, BarNumber)> + Clone,
+    {
+        let start = [self.find_lower_point(first), self.find_upper_point(first)];
+        start.iter().dedup().find_map(|&start| {
+            let mut sequence = *self;
+
+            for (bar, y) in offsets.clone() {
+                let (bar, y) = (first + bar, start + y);
+                let shorter = sequence.find_lower_point(bar);
+                let longer = sequence.find_upper_point(bar);
+                if shorter != longer {
+                    // the same mapping between bounds and points as `force`
+                    let further = (y == longer) == (bar >= 0);
+                    sequence.force(
+                        bar,
+                        if further {
+                            BarBound::Longer
+                        } else {
+                            BarBound::Shorter
+                        },
+                    );
+                }
+                if sequence.find_lower_point(bar) != y || sequence.find_upper_point(bar) != y {
+                    return None;
+                }
+            }
+
+            Some(sequence)
+        })
+    }
+
+    /// Copies the sequence with its intercept narrowed as far as bars up to `bars` either side of
+    /// the zeroeth can tell around `intercept`, forcing all of those bars.
+    pub(crate) fn with_intercept(&self, intercept: f64, bars: BarNumber) -> MusicalSequence {
+        let mut sequence = *self;
+        let (mut lower, mut upper) = (f64::NEG_INFINITY, f64::INFINITY);
+        for bar in -bars..=bars {
+            let point = (intercept + bar as f64 * golden_ratio::<f64>()).floor() as BarNumber;
+            let below = point as f64 - bar as f64 * golden_ratio::<f64>();
+            if below > lower {
+                lower = below;
+                sequence.lower_x = bar;
+                sequence.lower_y = point;
+            }
+            if below + 1f64 < upper {
+                upper = below + 1f64;
+                sequence.upper_x = bar;
+                sequence.upper_y = point + 1;
+            }
+        }
+        sequence
+    }
+
+    /// How far along its axis bar zero would be without forcing, and the least and greatest
+    /// intercepts forcing still allows, which together settle where every bar goes.
+    pub(crate) fn state(&self) -> [f64; 3] {
+        let (sin, cos) = self.rotation.sin_cos();
+        [
+            self.center_x * cos + self.center_y * sin,
+            self.find_lower_intercept(),
+            self.find_upper_intercept(),
+        ]
+    }
+
+    /// How far along the axis the other sequence's bars lie from this one's, if forcing allows
+    /// both the same bars. The sequences may number their bars differently: numbering from `k`
+    /// bars on moves the intercepts by a multiple of `k` times the golden ratio, which is undone
+    /// before comparing them.
+    pub(crate) fn shift_to(&self, other: &MusicalSequence) -> Option<f64> {
+        let renumbering = (other.lower_x - self.lower_x, other.lower_y - self.lower_y);
+        if (other.upper_x - self.upper_x, other.upper_y - self.upper_y) != renumbering {
+            return None;
+        }
+        let (bars, points) = renumbering;
+        Some(other.state()[0] + Self::distance_at(bars, points) - self.state()[0])
+    }
+
+    /// The sequence moved onto the axis at `rotation`, the same distances along it or, if
+    /// `reversed`, the same distances back along it.
+    pub(crate) fn carried(&self, rotation: f64, reversed: bool) -> MusicalSequence {
+        let [offset, ..] = self.state();
+        let mut carried = if reversed {
+            // reading backwards negates the bars and the intercepts
+            MusicalSequence {
+                upper_x: -self.lower_x,
+                upper_y: 1 - self.lower_y,
+                lower_x: -self.upper_x,
+                lower_y: 1 - self.upper_y,
+                ..*self
+            }
+        } else {
+            *self
+        };
+        carried.rotation = rotation;
+        carried.set_zeroeth(if reversed { -offset } else { offset });
+        carried
+    }
+
     pub(crate) fn center_x(&self) -> f64 {
         self.center_x
     }
@@ -214,7 +433,7 @@ impl MusicalSequence {
         self.center_y
     }
 
-    pub(crate) fn rotation(&self) -> f64 {
+    pub fn rotation(&self) -> f64 {
         self.rotation
     }
 }
@@ -237,6 +456,7 @@ impl Default for MusicalSequence {
 mod test {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     fn test_force<const N: usize>(
         ammann: MusicalSequence,
         r: Range<BarNumber>,
@@ -268,6 +488,58 @@ mod test {
         assert_eq!(lower_y, ammann.lower_y);
     }
 
+    #[test]
+    fn bars() {
+        let mut ammann = MusicalSequence::default();
+        ammann.force(1, BarBound::Longer);
+
+        let bars = ammann.bars(-1..6).collect_vec();
+        assert_eq!(
+            bars.iter().map(|bar| bar.number).collect_vec(),
+            (-1..6).collect_vec()
+        );
+        assert_eq!(bars[2].bound, BarBound::Longer);
+        assert!(bars[2].forced && !bars[3].forced);
+        assert!((bars[2].distance - long::<f64>()).abs() < epsilon::<f64>());
+
+        // bounds agree with the distances reported
+        for (previous, bar) in bars.iter().skip(1).tuple_windows() {
+            let gap = match bar.bound {
+                BarBound::Longer => long::<f64>(),
+                BarBound::Shorter => short::<f64>(),
+            };
+            assert!((bar.distance - previous.distance - gap).abs() < epsilon::<f64>());
+        }
+    }
+
+    #[test]
+    fn word_round_trip() {
+        let mut ammann = MusicalSequence::default();
+        ammann.force(1, BarBound::Longer);
+        ammann.force(-1, BarBound::Shorter);
+        let word = ammann.word(-8..8);
+        assert_eq!(word.len(), 16);
+        assert!(!word.contains("SS") && !word.contains("LLL"));
+
+        let fresh = MusicalSequence::default().with_word(-8, &word).unwrap();
+        assert_eq!(fresh.word(-8..8), word);
+        assert!(fresh
+            .get_bar_forcings(-8..9)
+            .into_iter()
+            .all(|forced| forced));
+
+        // conflicts with the bar already forced
+        assert_eq!(ammann.with_word(0, "S").unwrap_err(), WordError::Illegal);
+        assert_eq!(
+            MusicalSequence::default().with_word(0, "LSS").unwrap_err(),
+            WordError::Illegal
+        );
+        assert_eq!(
+            MusicalSequence::default().with_word(0, "LX").unwrap_err(),
+            WordError::Symbol(1, 'X')
+        );
+    }
+
     #[test]
     fn force_at_distance_short() {
         let mut ammann = MusicalSequence::default();
@@ -0,0 +1,310 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::hash::{Hash, Hasher};
+
+use euclid::default::{Point2D, Transform2D, Vector2D};
+use euclid::Angle;
+use rustc_hash::{FxHashMap, FxHasher};
+
+use super::constellation::Constellation;
+use super::dart::Dart;
+use super::identity::{PointKey, TileKey, TileLookup};
+use super::kite::Kite;
+use super::shape::Shape;
+use super::tiling::MatchList;
+
+/// A placement of a patch, and how far it is from the point it was searched from.
+#[derive(Debug, Copy, Clone)]
+pub struct Occurrence {
+    pub transform: Transform2D<f64>,
+    /// The distance from the point searched from to the middle of the placed patch.
+    pub distance: f64,
+}
+
+// interior angles at each corner of the tiles' paths, in degrees
+const KITE_ANGLES: [f64; 4] = [72f64, 72f64, 144f64, 72f64];
+const DART_ANGLES: [f64; 4] = [72f64, 36f64, 216f64, 36f64];
+
+/// A patch occurring in a tiling, up to rotation, and how often it occurs.
+#[derive(Debug, Clone)]
+pub struct PatchClass {
+    /// A hash of the canonical form of the patch, the same wherever it occurs.
+    pub hash: u64,
+    /// The patch in its canonical form, about the origin.
+    pub patch: MatchList,
+    pub frequency: usize,
+}
+
+// the tile's mapping alongside whether it is a kite
+fn tiles(list: &MatchList) -> impl Iterator<Item = (bool, Transform2D<f64>)> + '_ {
+    (list.kites.iter().map(|kite| (true, kite.mapping())))
+        .chain(list.darts.iter().map(|dart| (false, dart.mapping())))
+}
+
+// the middle of a patch, which moves with it
+pub(crate) fn centre(patch: &MatchList) -> Point2D<f64> {
+    let count = (patch.kites.len() + patch.darts.len()) as f64;
+    tiles(patch).fold(Point2D::origin(), |sum, (_, mapping)| {
+        sum + mapping.transform_point(Point2D::origin()).to_vector() / count
+    })
+}
+
+// how far the patch reaches from its middle
+pub(crate) fn extent(patch: &MatchList) -> f64 {
+    let centre = centre(patch);
+    (patch.kites.iter().flat_map(|kite| kite.path()))
+        .chain(patch.darts.iter().flat_map(|dart| dart.path()))
+        .map(|point| point.distance_to(centre))
+        .fold(0f64, f64::max)
+}
+
+// the smallest of the patch's keys when turned to lay each of its tiles along the x axis
+fn canonical(
+    tiles: &[(bool, Transform2D<f64>)],
+    centre: Point2D<f64>,
+) -> (Vec<(bool, TileKey)>, Transform2D<f64>) {
+    tiles
+        .iter()
+        .map(|&(_, mapping)| {
+            let axis = mapping.transform_vector(Vector2D::new(1f64, 0f64));
+            Transform2D::translation(-centre.x, -centre.y)
+                .then_rotate(Angle::radians(-axis.y.atan2(axis.x)))
+        })
+        .map(|transform| {
+            let mut keys = tiles
+                .iter()
+                .map(|&(kite, mapping)| (kite, TileKey::from(&mapping.then(&transform))))
+                .collect::<Vec<_>>();
+            keys.sort_unstable();
+            (keys, transform)
+        })
+        .min_by(|a, b| a.0.cmp(&b.0))
+        .unwrap()
+}
+
+impl MatchList {
+    /// Collects the patches around every vertex of the tiling: the tiles at each vertex within
+    /// `radius` of it, so a radius of zero gives the vertex stars. Patches with a vertex not
+    /// completely surrounded by tiles are left out.
+    pub fn atlas(&self, radius: f64) -> Vec<PatchClass> {
+        let mut incident = FxHashMap::<PointKey, (Point2D<f64>, f64, Vec<usize>)>::default();
+        let all = tiles(self).collect::<Vec<_>>();
+        let paths = (self.kites.iter().map(|kite| (kite.path(), KITE_ANGLES)))
+            .chain(self.darts.iter().map(|dart| (dart.path(), DART_ANGLES)));
+        for (i, (path, angles)) in paths.enumerate() {
+            for (&point, angle) in path.iter().zip(angles) {
+                let vertex = incident
+                    .entry(PointKey::from(point))
+                    .or_insert_with(|| (point, 0f64, Vec::new()));
+                vertex.1 += angle;
+                vertex.2.push(i);
+            }
+        }
+        let vertices = incident.values().collect::<Vec<_>>();
+        let surrounded = |angle: f64| (angle - 360f64).abs() < 1e-6;
+
+        let mut classes = FxHashMap::<Vec<(bool, TileKey)>, PatchClass>::default();
+        for &&(centre, _, _) in vertices.iter() {
+            let nearby = vertices
+                .iter()
+                .filter(|(point, _, _)| point.distance_to(centre) <= radius + 1e-6)
+                .collect::<Vec<_>>();
+            if !nearby.iter().all(|(_, angle, _)| surrounded(*angle)) {
+                continue;
+            }
+
+            let mut members = nearby
+                .iter()
+                .flat_map(|(_, _, tiles)| tiles.iter().copied())
+                .collect::<Vec<_>>();
+            members.sort_unstable();
+            members.dedup();
+            let patch = members.iter().map(|&i| all[i]).collect::<Vec<_>>();
+
+            let (keys, transform) = canonical(&patch, centre);
+            classes
+                .entry(keys)
+                .or_insert_with_key(|keys| {
+                    let mut hasher = FxHasher::default();
+                    keys.hash(&mut hasher);
+
+                    let mut patch = MatchList::default();
+                    for &(kite, mapping) in members.iter().map(|&i| &all[i]) {
+                        let mapping = mapping.then(&transform);
+                        if kite {
+                            patch.kites.push(Kite::new(mapping));
+                        } else {
+                            patch.darts.push(Dart::new(mapping, None, None));
+                        }
+                    }
+
+                    PatchClass {
+                        hash: hasher.finish(),
+                        patch,
+                        frequency: 0,
+                    }
+                })
+                .frequency += 1;
+        }
+
+        let mut classes = classes.into_values().collect::<Vec<_>>();
+        classes.sort_unstable_by(|a, b| b.frequency.cmp(&a.frequency).then(a.hash.cmp(&b.hash)));
+        classes
+    }
+
+    /// The number of distinct patches of the given radius, as counted by [`MatchList::atlas`].
+    pub fn complexity(&self, radius: f64) -> usize {
+        self.atlas(radius).len()
+    }
+
+    /// Finds every placement of a patch of tiles among these tiles, as the transforms carrying the
+    /// patch onto them. Tiles are placed at multiples of 36 degrees, so every rotation is covered;
+    /// with `reflections`, so are the patch's mirror images, with transforms which reflect it.
+    ///
+    /// A patch with symmetries is found once for each of them.
+    pub fn occurrences(&self, patch: &MatchList, reflections: bool) -> Vec<Transform2D<f64>> {
+        let (anchor_kite, anchor) = match tiles(patch).next() {
+            Some(anchor) => anchor,
+            None => return Vec::new(),
+        };
+        let inverse = anchor.inverse().unwrap();
+        let mirror = Transform2D::scale(1f64, -1f64);
+        let lookup = TileLookup::new(self);
+
+        tiles(self)
+            .filter(|&(kite, _)| kite == anchor_kite)
+            .flat_map(|(_, tile)| {
+                let direct = inverse.then(&tile);
+                let reflected = inverse.then(&mirror).then(&tile);
+                std::iter::once(direct).chain(reflections.then_some(reflected))
+            })
+            .filter(|transform| {
+                tiles(patch).all(|(kite, mapping)| {
+                    let mapping = mapping.then(transform);
+                    if kite {
+                        lookup.kite(&mapping).is_some()
+                    } else {
+                        lookup.dart(&mapping).is_some()
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Box2D;
+
+    use super::*;
+    use crate::dart::Dart;
+    use crate::kite::Kite;
+    use crate::shape::Shape;
+    use crate::{FiveFold, Tiling};
+
+    fn transformed(list: &MatchList, transform: &Transform2D<f64>) -> MatchList {
+        // keep the tiles' own mappings rigid under reflection
+        let local = if transform.determinant() < 0f64 {
+            Transform2D::scale(1f64, -1f64)
+        } else {
+            Transform2D::identity()
+        };
+        MatchList {
+            kites: (list.kites.iter())
+                .map(|kite| Kite::new(local.then(&kite.mapping()).then(transform)))
+                .collect(),
+            darts: (list.darts.iter())
+                .map(|dart| Dart::new(local.then(&dart.mapping()).then(transform), None, None))
+                .collect(),
+        }
+    }
+
+    fn close(a: &Transform2D<f64>, b: &Transform2D<f64>) -> bool {
+        (a.to_array().iter())
+            .zip(b.to_array())
+            .all(|(a, b)| (a - b).abs() < 1e-6)
+    }
+
+    #[test]
+    fn vertex_neighbourhood() {
+        let bounds = Box2D::new(Point2D::new(-15f64, -15f64), Point2D::new(15f64, 15f64));
+        let tiles = Tiling::new(FiveFold::king_configuration(), bounds).compute_area();
+
+        // every tile around the apex of the kite nearest the middle
+        let vertex = (tiles.kites.iter())
+            .map(|kite| kite.mapping().transform_point(Point2D::origin()))
+            .min_by(|a, b| {
+                a.to_vector()
+                    .length()
+                    .partial_cmp(&b.to_vector().length())
+                    .unwrap()
+            })
+            .unwrap();
+        let touches = |path: [Point2D<f64>; 4]| path.iter().any(|p| p.distance_to(vertex) < 1e-6);
+        let patch = MatchList {
+            kites: (tiles.kites.iter())
+                .filter(|kite| touches(kite.path()))
+                .copied()
+                .collect(),
+            darts: (tiles.darts.iter())
+                .filter(|dart| touches(dart.path()))
+                .cloned()
+                .collect(),
+        };
+        assert!(patch.kites.len() + patch.darts.len() >= 3);
+
+        let found = tiles.occurrences(&patch, false);
+        assert!(found.iter().any(|t| close(t, &Transform2D::identity())));
+        assert!(found.iter().all(|t| t.determinant() > 0f64));
+
+        let moved = Transform2D::rotation(Angle::degrees(72f64))
+            .then_translate(Vector2D::new(100f64, -3f64));
+        let found = tiles.occurrences(&transformed(&patch, &moved), false);
+        assert!(found.iter().any(|t| close(t, &moved.inverse().unwrap())));
+
+        let mirrored = transformed(&patch, &Transform2D::scale(-1f64, 1f64));
+        let found = tiles.occurrences(&mirrored, true);
+        assert!(found.iter().any(|t| t.determinant() < 0f64));
+    }
+
+    #[test]
+    fn vertex_atlas() {
+        let bounds = Box2D::new(Point2D::new(-15f64, -15f64), Point2D::new(15f64, 15f64));
+        let tiles = Tiling::new(FiveFold::king_configuration(), bounds).compute_area();
+
+        // sun, star, ace, deuce, jack, queen and king
+        let stars = tiles.atlas(0f64);
+        assert_eq!(stars.len(), 7);
+        for class in stars.iter() {
+            let around = (class.patch.kites.iter())
+                .map(|kite| kite.path())
+                .chain(class.patch.darts.iter().map(|dart| dart.path()))
+                .filter(|path| path.iter().any(|point| point.to_vector().length() < 1e-6))
+                .count();
+            assert_eq!(around, class.patch.kites.len() + class.patch.darts.len());
+        }
+
+        let rings = tiles.atlas(1f64);
+        assert!(rings.len() > stars.len());
+        assert_eq!(tiles.complexity(1f64), rings.len());
+        assert!(rings
+            .windows(2)
+            .all(|pair| pair[0].frequency >= pair[1].frequency));
+    }
+}
@@ -0,0 +1,381 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use euclid::default::{Point2D, Transform2D, Vector2D};
+use euclid::Angle;
+use lazy_static::lazy_static;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::constants::golden_ratio;
+use super::constellation::Constellation;
+use super::identity::PointKey;
+use super::shape::Shape;
+use super::tiling::MatchList;
+
+lazy_static! {
+    // a pentagon centred on the nose of a dart, with a corner along its axis
+    static ref PENTAGON: [Point2D<f64>; 5] =
+        [0, 1, 2, 3, 4].map(|k| polar(72f64 * k as f64, golden_ratio::<f64>()));
+    // points of the star reach out to the square of the golden ratio, between corners of the unit
+    // pentagon in its middle
+    static ref PENTAGRAM: [Point2D<f64>; 10] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9].map(|k| {
+        let radius = if k % 2 == 0 {
+            golden_ratio::<f64>().powi(2)
+        } else {
+            1f64
+        };
+        polar(36f64 * k as f64, radius)
+    });
+    // the three points of a pentagram about the x axis, cut off across its middle pentagon
+    static ref BOAT: [Point2D<f64>; 7] = [0, 1, 2, 3, 4, 5, 6].map(|k| PENTAGRAM[(k + 7) % 10]);
+    static ref DIAMOND: [Point2D<f64>; 4] = {
+        let edge = 2f64 * golden_ratio::<f64>() * 36f64.to_radians().sin();
+        [0, 1, 2, 3].map(|k| {
+            let radius = if k % 2 == 0 {
+                edge * 18f64.to_radians().cos()
+            } else {
+                edge * 18f64.to_radians().sin()
+            };
+            polar(90f64 * k as f64, radius)
+        })
+    };
+}
+
+fn polar(degrees: f64, radius: f64) -> Point2D<f64> {
+    Vector2D::from_angle_and_length(Angle::degrees(degrees), radius).to_point()
+}
+
+fn angle(vector: Vector2D<f64>) -> f64 {
+    vector.y.atan2(vector.x)
+}
+
+fn place(centre: Point2D<f64>, towards: Point2D<f64>) -> Transform2D<f64> {
+    Transform2D::rotation(Angle::radians(angle(towards - centre)))
+        .then_translate(centre.to_vector())
+}
+
+// even-odd rule, since stars and boats aren't convex
+fn contains<const N: usize>(
+    path: &[Point2D<f64>; N],
+    mapping: &Transform2D<f64>,
+    point: Point2D<f64>,
+) -> bool {
+    let point = mapping.inverse().unwrap().transform_point(point);
+
+    let mut inside = false;
+    for (i, a) in path.iter().enumerate() {
+        let b = path[(i + 1) % N];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (b.x - a.x) * (point.y - a.y) / (b.y - a.y)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// A regular pentagon.
+#[derive(Debug, Copy, Clone)]
+pub struct Pentagon {
+    mapping: Transform2D<f64>,
+}
+
+impl Pentagon {
+    pub fn mapping(&self) -> Transform2D<f64> {
+        self.mapping
+    }
+}
+
+impl Shape<5> for Pentagon {
+    fn contains(&self, point: Point2D<f64>) -> bool {
+        contains(&PENTAGON, &self.mapping, point)
+    }
+
+    fn path(&self) -> [Point2D<f64>; 5] {
+        PENTAGON.map(|point| self.mapping.transform_point(point))
+    }
+}
+
+/// A five pointed star.
+#[derive(Debug, Copy, Clone)]
+pub struct Pentagram {
+    mapping: Transform2D<f64>,
+}
+
+impl Pentagram {
+    pub fn mapping(&self) -> Transform2D<f64> {
+        self.mapping
+    }
+}
+
+impl Shape<10> for Pentagram {
+    fn contains(&self, point: Point2D<f64>) -> bool {
+        contains(&PENTAGRAM, &self.mapping, point)
+    }
+
+    fn path(&self) -> [Point2D<f64>; 10] {
+        PENTAGRAM.map(|point| self.mapping.transform_point(point))
+    }
+}
+
+/// Three adjacent points of a star, cut off across its middle.
+#[derive(Debug, Copy, Clone)]
+pub struct Boat {
+    mapping: Transform2D<f64>,
+}
+
+impl Boat {
+    pub fn mapping(&self) -> Transform2D<f64> {
+        self.mapping
+    }
+}
+
+impl Shape<7> for Boat {
+    fn contains(&self, point: Point2D<f64>) -> bool {
+        contains(&BOAT, &self.mapping, point)
+    }
+
+    fn path(&self) -> [Point2D<f64>; 7] {
+        BOAT.map(|point| self.mapping.transform_point(point))
+    }
+}
+
+/// A rhombus with angles of 36 and 144 degrees.
+#[derive(Debug, Copy, Clone)]
+pub struct Diamond {
+    mapping: Transform2D<f64>,
+}
+
+impl Diamond {
+    pub fn mapping(&self) -> Transform2D<f64> {
+        self.mapping
+    }
+}
+
+impl Shape<4> for Diamond {
+    fn contains(&self, point: Point2D<f64>) -> bool {
+        contains(&DIAMOND, &self.mapping, point)
+    }
+
+    fn path(&self) -> [Point2D<f64>; 4] {
+        DIAMOND.map(|point| self.mapping.transform_point(point))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PentagonalList {
+    pub pentagons: Vec<Pentagon>,
+    pub pentagrams: Vec<Pentagram>,
+    pub boats: Vec<Boat>,
+    pub diamonds: Vec<Diamond>,
+}
+
+// the interior angle at each corner of an anticlockwise loop is under 90 degrees only at the points
+fn points(corners: &[Point2D<f64>]) -> Vec<usize> {
+    let n = corners.len();
+    (0..n)
+        .filter(|&i| {
+            let (prev, point, next) = (corners[(i + n - 1) % n], corners[i], corners[(i + 1) % n]);
+            (next - point).angle_to(prev - point).radians.abs() < std::f64::consts::FRAC_PI_2
+        })
+        .collect()
+}
+
+impl PentagonalList {
+    fn push_gap(&mut self, corners: &[Point2D<f64>]) {
+        let n = corners.len();
+        let centre = corners.iter().fold(Point2D::origin(), |sum, point| {
+            sum + point.to_vector() / n as f64
+        });
+        let points = points(corners);
+
+        match (n, points.as_slice()) {
+            (4, &[point, ..]) => self.diamonds.push(Diamond {
+                mapping: place(centre, corners[point]),
+            }),
+            (10, &[point, ..]) => self.pentagrams.push(Pentagram {
+                mapping: place(centre, corners[point]),
+            }),
+            (7, &[_, _, _]) => {
+                // the middle point is two corners along from both of the others
+                let middle = points
+                    .iter()
+                    .copied()
+                    .find(|&i| {
+                        points.contains(&((i + 2) % n)) && points.contains(&((i + n - 2) % n))
+                    })
+                    .unwrap();
+                let cut = corners[(middle + 3) % n].lerp(corners[(middle + n - 3) % n], 0.5);
+                let direction = (corners[middle] - cut).normalize();
+                let centre = corners[middle] - direction * golden_ratio::<f64>().powi(2);
+                self.boats.push(Boat {
+                    mapping: place(centre, corners[middle]),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+impl MatchList {
+    /// Converts the tiling into Penrose's original tiling of pentagons, pentagrams, boats and
+    /// diamonds. Every vertex with the nose of a dart is the centre of a pentagon with a corner
+    /// along the axis of that dart, and the gaps the pentagons leave between them are the rest of
+    /// the tiles.
+    ///
+    /// Gaps not completely surrounded by pentagons are left out.
+    pub fn pentagonal(&self) -> PentagonalList {
+        let mut list = PentagonalList::default();
+
+        let mut noses = FxHashSet::default();
+        for dart in self.darts.iter() {
+            let mapping = dart.mapping();
+            if noses.insert(PointKey::from(mapping.transform_point(Point2D::origin()))) {
+                list.pentagons.push(Pentagon { mapping });
+            }
+        }
+
+        let mut edges = FxHashMap::default();
+        for pentagon in list.pentagons.iter() {
+            let path = pentagon.path();
+            for (i, &a) in path.iter().enumerate() {
+                let b = path[(i + 1) % 5];
+                edges.insert((PointKey::from(a), PointKey::from(b)), (a, b));
+            }
+        }
+
+        // edges with no pentagon on the other side, reversed to leave the gap on their left
+        let mut free = Vec::new();
+        let mut leaving = FxHashMap::<_, Vec<_>>::default();
+        for (&(a, b), &(from, _)) in edges.iter() {
+            if !edges.contains_key(&(b, a)) {
+                free.push((b, a));
+                leaving.entry(b).or_default().push(from);
+            }
+        }
+        free.sort_unstable();
+
+        let mut used = FxHashSet::default();
+        for &(start, next) in free.iter() {
+            if used.contains(&(start, next)) {
+                continue;
+            }
+
+            let mut corners = Vec::new();
+            let (mut from, mut to) = (edges[&(next, start)].1, edges[&(next, start)].0);
+            loop {
+                let (key_from, key_to) = (PointKey::from(from), PointKey::from(to));
+                if !used.insert((key_from, key_to)) {
+                    break;
+                }
+                corners.push(from);
+                // no gap tile has more than ten corners; anything longer is the outside edge
+                if corners.len() > 10 {
+                    break;
+                }
+
+                // the edge the gap continues along is the first one clockwise from the way back
+                let back = angle(from - to);
+                let turn = |point: &&Point2D<f64>| {
+                    let turn = (back - angle(**point - to)).rem_euclid(std::f64::consts::TAU);
+                    if turn == 0f64 {
+                        std::f64::consts::TAU
+                    } else {
+                        turn
+                    }
+                };
+                let onwards = match leaving.get(&key_to).and_then(|points| {
+                    points
+                        .iter()
+                        .min_by(|a, b| turn(a).partial_cmp(&turn(b)).unwrap())
+                }) {
+                    Some(&point) => point,
+                    None => break,
+                };
+                from = to;
+                to = onwards;
+            }
+
+            if PointKey::from(from) == PointKey::from(corners[0]) && corners.len() <= 10 {
+                list.push_gap(&corners);
+            }
+        }
+
+        list
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::sun_patch;
+
+    #[test]
+    fn deflated_sun() {
+        let tiles = sun_patch(5);
+        let list = tiles.pentagonal();
+
+        assert!(!list.pentagons.is_empty());
+        assert!(!list.pentagrams.is_empty());
+        assert!(!list.boats.is_empty());
+        assert!(!list.diamonds.is_empty());
+
+        let edge = 2f64 * golden_ratio::<f64>() * 36f64.to_radians().sin();
+        fn edges<const N: usize>(path: [Point2D<f64>; N], edge: f64) {
+            for (i, a) in path.iter().enumerate() {
+                assert!((a.distance_to(path[(i + 1) % N]) - edge).abs() < 1e-6);
+            }
+        }
+        list.pentagons.iter().for_each(|t| edges(t.path(), edge));
+        list.pentagrams.iter().for_each(|t| edges(t.path(), edge));
+        list.boats.iter().for_each(|t| edges(t.path(), edge));
+        list.diamonds.iter().for_each(|t| edges(t.path(), edge));
+
+        // no two tiles overlap
+        let centres = list
+            .pentagons
+            .iter()
+            .map(|t| t.mapping().transform_point(Point2D::origin()))
+            .chain(
+                list.pentagrams
+                    .iter()
+                    .map(|t| t.mapping().transform_point(Point2D::origin())),
+            )
+            .chain(
+                list.diamonds
+                    .iter()
+                    .map(|t| t.mapping().transform_point(Point2D::origin())),
+            )
+            .chain(
+                list.boats
+                    .iter()
+                    .map(|t| t.mapping().transform_point(Point2D::new(1f64, 0f64))),
+            );
+        for centre in centres {
+            let count = list.pentagons.iter().filter(|t| t.contains(centre)).count()
+                + list
+                    .pentagrams
+                    .iter()
+                    .filter(|t| t.contains(centre))
+                    .count()
+                + list.boats.iter().filter(|t| t.contains(centre)).count()
+                + list.diamonds.iter().filter(|t| t.contains(centre)).count();
+            assert_eq!(count, 1);
+        }
+    }
+}
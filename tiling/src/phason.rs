@@ -0,0 +1,412 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use euclid::default::{Box2D, Point2D, Vector2D};
+use rustc_hash::FxHashMap;
+
+use super::constants::{golden_ratio, short};
+use super::diff::TilingDiff;
+use super::fivefold::FiveFold;
+use super::musical_sequence::BarNumber;
+use super::shape::Shape;
+use super::tiling::{MatchList, Tiling};
+
+const N: usize = 5;
+
+// the largest denominator tried when recovering an offset as a + b√5 with rational a and b
+const DENOMINATOR: i64 = 40;
+const NUMERATOR: i64 = 400;
+
+/// The tiles of a plane at one point along a phason path.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// How far along the path, from 0 to 1.
+    pub time: f64,
+    /// Where the path is in the phason space of the plane, as for [`FiveFold::with_phason`].
+    pub phason: Vector2D<f64>,
+    /// The intercept of each sequence, which places its long and short gaps as the offset of a
+    /// de Bruijn grid places its lines.
+    pub intercepts: Vec<f64>,
+    /// The tiles centred in the area.
+    pub tiles: MatchList,
+    /// The tiles which changed since the last frame, as local flips: each group changed as one bar
+    /// moved.
+    pub flips: Vec<TilingDiff>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PhasonError {
+    /// The zeroeth bars of the sequences aren't placed as any Penrose tiling's are. Which of them
+    /// is out of place can't be told, as the translation taken out spreads it over them all.
+    Offsets,
+}
+
+impl Display for PhasonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhasonError::Offsets => {
+                write!(f, "the sequences are not offset as in a Penrose tiling")
+            }
+        }
+    }
+}
+
+impl Error for PhasonError {}
+
+// swaps the sign of √5 in a number of the form (p + qφ) / d
+fn conjugate(value: f64) -> Option<f64> {
+    let phi = golden_ratio::<f64>();
+    (1..=DENOMINATOR).find_map(|denominator| {
+        (-NUMERATOR..=NUMERATOR).find_map(|q| {
+            let p = denominator as f64 * value - q as f64 * phi;
+            ((p - p.round()).abs() < 1e-9 * denominator as f64)
+                .then(|| (p.round() + q as f64 * (1f64 - phi)) / denominator as f64)
+        })
+    })
+}
+
+// the direction in phason space which moves the sequence's intercept, turning twice as fast
+fn phason_axis(rotation: f64) -> Vector2D<f64> {
+    let (sin, cos) = (2f64 * rotation).sin_cos();
+    Vector2D::new(cos, sin)
+}
+
+fn centre<const M: usize>(shape: &impl Shape<M>) -> Point2D<f64> {
+    (shape
+        .path()
+        .iter()
+        .fold(Vector2D::zero(), |sum, corner| sum + corner.to_vector())
+        / M as f64)
+        .to_point()
+}
+
+fn centred(tiles: MatchList, bounds: &Box2D<f64>) -> MatchList {
+    MatchList {
+        kites: (tiles.kites.into_iter())
+            .filter(|kite| bounds.contains(centre(kite)))
+            .collect(),
+        darts: (tiles.darts.into_iter())
+            .filter(|dart| bounds.contains(centre(dart)))
+            .collect(),
+    }
+}
+
+// the bars which moved between the planes, each as the axis of its sequence and its distances
+// along it before and after
+fn moved_bars(
+    last: &FiveFold,
+    plane: &FiveFold,
+    bounds: &Box2D<f64>,
+) -> Vec<(Vector2D<f64>, [f64; 2])> {
+    let distances = |plane: &FiveFold| {
+        (plane.bars_in(bounds).into_iter())
+            .map(|bar| {
+                let (sin, cos) = plane.sequences()[bar.sequence].rotation().sin_cos();
+                let axis = Vector2D::new(cos, sin);
+                (
+                    (bar.sequence, bar.bar),
+                    (axis, bar.start.to_vector().dot(axis)),
+                )
+            })
+            .collect::<FxHashMap<_, _>>()
+    };
+    let (before, after) = (distances(last), distances(plane));
+
+    let mut moved = Vec::new();
+    for (key, &(axis, distance)) in before.iter() {
+        match after.get(key) {
+            Some(&(_, moved_to)) if (moved_to - distance).abs() < 1e-9 => {}
+            Some(&(_, moved_to)) => moved.push((*key, axis, [distance, moved_to])),
+            None => moved.push((*key, axis, [distance; 2])),
+        }
+    }
+    for (key, &(axis, distance)) in after.iter() {
+        if !before.contains_key(key) {
+            moved.push((*key, axis, [distance; 2]));
+        }
+    }
+    moved.sort_by_key(|&(key, ..)| key);
+    moved
+        .into_iter()
+        .map(|(_, axis, distances)| (axis, distances))
+        .collect()
+}
+
+// splits the changes into local flips, by the moved bar each changed tile lies nearest to
+fn flips(diff: &TilingDiff, moved: &[(Vector2D<f64>, [f64; 2])]) -> Vec<TilingDiff> {
+    let nearest = |centre: Point2D<f64>| {
+        let away = |&(axis, distances): &(Vector2D<f64>, [f64; 2])| {
+            let along = centre.to_vector().dot(axis);
+            (along - distances[0])
+                .abs()
+                .min((along - distances[1]).abs())
+        };
+        (0..moved.len())
+            .min_by(|&a, &b| away(&moved[a]).partial_cmp(&away(&moved[b])).unwrap())
+            .unwrap_or(0)
+    };
+
+    let mut groups = vec![TilingDiff::default(); moved.len().max(1)];
+    for (tiles, added) in [(&diff.added, true), (&diff.removed, false)] {
+        for kite in tiles.kites.iter() {
+            let group = &mut groups[nearest(centre(kite))];
+            match added {
+                true => group.added.kites.push(*kite),
+                false => group.removed.kites.push(*kite),
+            }
+        }
+        for dart in tiles.darts.iter() {
+            let group = &mut groups[nearest(centre(dart))];
+            match added {
+                true => group.added.darts.push(dart.clone()),
+                false => group.removed.darts.push(dart.clone()),
+            }
+        }
+    }
+    groups.retain(|group| !group.is_empty());
+    groups
+}
+
+impl FiveFold {
+    // the intercept of each sequence at the origin of phason space
+    fn phason_origin(&self) -> Result<[f64; N], PhasonError> {
+        let offsets = self.sequences().iter().map(|ms| ms.state()[0]);
+        let axes = self.sequences().iter().map(|ms| {
+            let (sin, cos) = ms.rotation().sin_cos();
+            Vector2D::new(cos, sin)
+        });
+
+        // translating the plane only moves it in phason space, so take the translation out first
+        let translation = (offsets.clone().zip(axes.clone()))
+            .fold(Vector2D::zero(), |sum, (offset, axis)| sum + axis * offset)
+            * (2f64 / N as f64);
+
+        let mut origin = [0f64; N];
+        for (i, (offset, axis)) in offsets.zip(axes).enumerate() {
+            let conjugate =
+                conjugate(offset - translation.dot(axis)).ok_or(PhasonError::Offsets)?;
+            origin[i] = 0.5 + 2f64 * conjugate / 5f64.sqrt();
+        }
+        Ok(origin)
+    }
+
+    /// Where the plane is in its phason space: the Penrose tiling whose intercepts come closest to
+    /// those the plane's forcings still allow. Fails if the zeroeth bars aren't placed as any
+    /// Penrose tiling's are, in which case neither is anything else.
+    pub fn phason(&self) -> Result<Vector2D<f64>, PhasonError> {
+        let origin = self.phason_origin()?;
+
+        // the axes of phason space form a tight frame, so this is the least squares fit
+        Ok(origin.iter().zip(self.sequences().iter()).fold(
+            Vector2D::zero(),
+            |sum, (origin, ms)| {
+                let [_, lower, upper] = ms.state();
+                sum + phason_axis(ms.rotation()) * ((lower + upper) / 2f64 - origin)
+            },
+        ) * (-1f64 / 5f64.sqrt()))
+    }
+
+    /// The plane moved to `phason` in its phason space, keeping the zeroeth bars where they are and
+    /// settling every bar within `radius` of the origin. The intercepts of the sequences move
+    /// together, each along its own axis of the phason space, so the plane stays a Penrose tiling
+    /// wherever it goes.
+    pub fn with_phason(&self, phason: Vector2D<f64>, radius: f64) -> Result<FiveFold, PhasonError> {
+        let origin = self.phason_origin()?;
+
+        // a fresh plane, as the bars the intersections were cached for all move
+        let mut plane = FiveFold::default();
+        plane.sequences_mut().copy_from_slice(self.sequences());
+        for (ms, origin) in plane.sequences_mut().iter_mut().zip(origin.iter()) {
+            let intercept = origin - 2f64 * phason.dot(phason_axis(ms.rotation())) / 5f64.sqrt();
+            let bars = ((radius + ms.state()[0].abs()) / short::<f64>()).ceil() as BarNumber + 1;
+            *ms = ms.with_intercept(intercept, bars);
+        }
+        Ok(plane)
+    }
+}
+
+impl Tiling {
+    /// Computes the tiles centred in the area as the plane moves steadily through its phason
+    /// space by `shift`, from where [`FiveFold::phason`] places it, at `frames` steps after the
+    /// start. Each frame records how its tiles changed from the last as local flips.
+    pub fn phason_path(
+        &self,
+        shift: Vector2D<f64>,
+        frames: usize,
+    ) -> Result<Vec<Frame>, PhasonError> {
+        let bounds = self.bounds();
+        // enough room around the area for the tiles centred in it to be found whole
+        let margin = 2f64 * golden_ratio::<f64>();
+        let computed = bounds.inflate(margin, margin);
+        let radius = [
+            computed.min,
+            computed.max,
+            Point2D::new(computed.min.x, computed.max.y),
+            Point2D::new(computed.max.x, computed.min.y),
+        ]
+        .iter()
+        .map(|corner| corner.to_vector().length())
+        .fold(0f64, f64::max);
+
+        let start = self.plane().phason()?;
+        let mut path = Vec::<Frame>::with_capacity(frames + 1);
+        let mut last = None;
+        for step in 0..=frames {
+            let time = match frames {
+                0 => 0f64,
+                _ => step as f64 / frames as f64,
+            };
+            let phason = start + shift * time;
+
+            let plane = self.plane().with_phason(phason, radius)?;
+            let intercepts = (plane.sequences().iter())
+                .map(|ms| ms.state()[1])
+                .collect::<Vec<_>>();
+            let moved = (last.as_ref()).map(|last| moved_bars(last, &plane, &computed));
+            last = Some(plane.clone());
+            let tiles = centred(Tiling::new(plane, computed).compute_area(), &bounds);
+            let flips = match (path.last(), moved) {
+                (Some(frame), Some(moved)) => flips(&frame.tiles.diff(&tiles), &moved),
+                _ => Vec::new(),
+            };
+            path.push(Frame {
+                time,
+                phason,
+                intercepts,
+                tiles,
+                flips,
+            });
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn phason() {
+        // the phason found for a plane agrees with its forcings
+        let mut tiling = Tiling::new(
+            FiveFold::king_configuration(),
+            Box2D::new(Point2D::new(-10f64, -10f64), Point2D::new(10f64, 10f64)),
+        );
+        tiling.compute_area();
+        let plane = tiling.plane();
+        let phason = plane.phason().unwrap();
+        let moved = plane.with_phason(phason, 10f64).unwrap();
+        for (ms, moved) in plane.sequences().iter().zip(moved.sequences()) {
+            let [offset, lower, upper] = ms.state();
+            let [moved_offset, moved_lower, moved_upper] = moved.state();
+            assert!((offset - moved_offset).abs() < 1e-9);
+            assert!(lower <= moved_lower && moved_upper <= upper);
+        }
+
+        // however far the plane moves, it ends up where it was sent
+        let shift = Vector2D::new(0.6, -0.9);
+        let moved = plane.with_phason(phason + shift, 10f64).unwrap();
+        assert!((moved.phason().unwrap() - phason - shift).length() < 0.05);
+
+        // translating the plane leaves it where it was in phason space
+        let mut translated = plane.clone();
+        for ms in translated.sequences_mut() {
+            let (sin, cos) = ms.rotation().sin_cos();
+            ms.set_zeroeth(ms.state()[0] + 0.3 * cos + 0.7 * sin);
+        }
+        assert!((translated.phason().unwrap() - phason).length() < 1e-9);
+
+        // taking out the translation spreads one misplaced bar across every sequence
+        let mut broken = FiveFold::king_configuration();
+        broken.sequences_mut()[2].set_zeroeth(std::f64::consts::PI);
+        assert_eq!(broken.phason(), Err(PhasonError::Offsets));
+    }
+
+    #[test]
+    fn phason_path() {
+        let bounds = Box2D::new(Point2D::new(-5f64, -5f64), Point2D::new(5f64, 5f64));
+        let tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        let path = tiling.phason_path(Vector2D::new(0.6, -0.9), 12).unwrap();
+        assert_eq!(path.len(), 13);
+        assert!(path[0].flips.is_empty());
+
+        let count = |tiles: &MatchList| tiles.kites.len() + tiles.darts.len();
+        for (last, frame) in path.iter().zip(path.iter().skip(1)) {
+            let diff = last.tiles.diff(&frame.tiles);
+            let flipped = frame.flips.iter();
+            assert_eq!(
+                flipped
+                    .clone()
+                    .map(|flip| count(&flip.added))
+                    .sum::<usize>(),
+                count(&diff.added)
+            );
+            assert_eq!(
+                flipped.map(|flip| count(&flip.removed)).sum::<usize>(),
+                count(&diff.removed)
+            );
+        }
+        assert!(path.iter().any(|frame| !frame.flips.is_empty()));
+        // bars of several sequences move between some frames, each flipping tiles of its own
+        assert!(path.iter().any(|frame| frame.flips.len() > 1));
+
+        // the tiles keep covering the area, however far the plane has moved
+        let area = |tiles: &MatchList| {
+            let paths = (tiles.kites.iter().map(|kite| kite.path()))
+                .chain(tiles.darts.iter().map(|dart| dart.path()));
+            paths
+                .map(|path| {
+                    (0..4)
+                        .map(|i| path[i].to_vector().cross(path[(i + 1) % 4].to_vector()))
+                        .sum::<f64>()
+                        .abs()
+                        / 2f64
+                })
+                .sum::<f64>()
+        };
+        for frame in path.iter() {
+            assert!((area(&frame.tiles) / area(&path[0].tiles) - 1f64).abs() < 0.1);
+        }
+
+        // the whole path is in phason space, so the tiles keep coming out legal
+        for frame in path.iter() {
+            assert_eq!(frame.tiles.verify(), Ok(()));
+        }
+        let end = &path[12];
+        assert!((end.phason - path[0].phason - Vector2D::new(0.6, -0.9)).length() < 1e-9);
+        assert!(!path[0].tiles.diff(&end.tiles).is_empty());
+
+        // intersections already cached for a computed plane don't leak into the frames
+        let mut computed = Tiling::new(FiveFold::king_configuration(), bounds);
+        computed.compute_area();
+        let mut fresh = FiveFold::default();
+        fresh
+            .sequences_mut()
+            .copy_from_slice(computed.plane().sequences());
+        let fresh = Tiling::new(fresh, bounds).phason_path(Vector2D::new(0.6, -0.9), 12);
+        let computed = computed.phason_path(Vector2D::new(0.6, -0.9), 12).unwrap();
+        for (frame, fresh) in computed.iter().zip(fresh.unwrap().iter()) {
+            assert!(frame.tiles.diff(&fresh.tiles).is_empty());
+            assert_eq!(frame.tiles.verify(), Ok(()));
+            assert!((area(&frame.tiles) / area(&path[0].tiles) - 1f64).abs() < 0.1);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use arrayvec::ArrayVec;
+use euclid::default::{Box2D, Point2D, Transform2D};
+use euclid::Angle;
+use lazy_static::lazy_static;
+use rustc_hash::FxHashMap;
+
+use super::constants::golden_ratio;
+use super::identity::PointKey;
+use super::robinson::TriangleKind;
+use super::shape::{Shape, Triangle};
+use super::tiling::MatchList;
+
+lazy_static! {
+    static ref THICK_TRIANGLES: [Triangle; 2] = triangles(36f64);
+    static ref THICK_BOUNDING_BOX: Box2D<f64> = bounding_box(&THICK_TRIANGLES);
+    static ref THIN_TRIANGLES: [Triangle; 2] = triangles(18f64);
+    static ref THIN_BOUNDING_BOX: Box2D<f64> = bounding_box(&THIN_TRIANGLES);
+}
+
+// unit rhombi with an acute corner at the origin and the other across the x axis from it
+fn triangles(half_angle: f64) -> [Triangle; 2] {
+    let (sin, cos) = half_angle.to_radians().sin_cos();
+
+    [
+        Triangle {
+            a: Point2D::new(0f64, 0f64),
+            b: Point2D::new(2f64 * cos, 0f64),
+            c: Point2D::new(cos, sin),
+        },
+        Triangle {
+            a: Point2D::new(0f64, 0f64),
+            b: Point2D::new(2f64 * cos, 0f64),
+            c: Point2D::new(cos, -sin),
+        },
+    ]
+}
+
+fn bounding_box(triangles: &[Triangle; 2]) -> Box2D<f64> {
+    Box2D::new(
+        Point2D::new(0f64, triangles[1].c.y),
+        Point2D::new(triangles[0].b.x, triangles[0].c.y),
+    )
+}
+
+fn contains(
+    triangles: &[Triangle; 2],
+    bounding_box: &Box2D<f64>,
+    mapping: &Transform2D<f64>,
+    point: Point2D<f64>,
+) -> bool {
+    let inverse = mapping.inverse().unwrap();
+    let point = inverse.transform_point(point);
+
+    // heuristic: check if we're even in the bounding box
+    if bounding_box.contains(point) {
+        if point.y >= 0f64 {
+            triangles[0].contains(point)
+        } else {
+            triangles[1].contains(point)
+        }
+    } else {
+        false
+    }
+}
+
+fn path(triangles: &[Triangle; 2], mapping: &Transform2D<f64>) -> [Point2D<f64>; 4] {
+    [
+        mapping.transform_point(triangles[0].a),
+        mapping.transform_point(triangles[0].c),
+        mapping.transform_point(triangles[0].b),
+        mapping.transform_point(triangles[1].c),
+    ]
+}
+
+// maps the local acute corners onto `from` and `to`
+fn place(from: Point2D<f64>, to: Point2D<f64>) -> Transform2D<f64> {
+    let direction = to - from;
+    Transform2D::rotation(Angle::radians(direction.y.atan2(direction.x)))
+        .then_translate(from.to_vector())
+}
+
+/// A rhombus with angles of 72 and 108 degrees.
+#[derive(Debug, Copy, Clone)]
+pub struct ThickRhombus {
+    mapping: Transform2D<f64>,
+}
+
+impl ThickRhombus {
+    pub fn mapping(&self) -> Transform2D<f64> {
+        self.mapping
+    }
+}
+
+impl Shape<4> for ThickRhombus {
+    fn contains(&self, point: Point2D<f64>) -> bool {
+        contains(&THICK_TRIANGLES, &THICK_BOUNDING_BOX, &self.mapping, point)
+    }
+
+    fn path(&self) -> [Point2D<f64>; 4] {
+        path(&THICK_TRIANGLES, &self.mapping)
+    }
+}
+
+/// A rhombus with angles of 36 and 144 degrees.
+#[derive(Debug, Copy, Clone)]
+pub struct ThinRhombus {
+    mapping: Transform2D<f64>,
+}
+
+impl ThinRhombus {
+    pub fn mapping(&self) -> Transform2D<f64> {
+        self.mapping
+    }
+}
+
+impl Shape<4> for ThinRhombus {
+    fn contains(&self, point: Point2D<f64>) -> bool {
+        contains(&THIN_TRIANGLES, &THIN_BOUNDING_BOX, &self.mapping, point)
+    }
+
+    fn path(&self) -> [Point2D<f64>; 4] {
+        path(&THIN_TRIANGLES, &self.mapping)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RhombusList {
+    pub thick: Vec<ThickRhombus>,
+    pub thin: Vec<ThinRhombus>,
+}
+
+type Edge = [Point2D<f64>; 2];
+
+// the triangles either side of each edge, by their corners opposite it
+#[derive(Default)]
+struct Pairs {
+    edges: FxHashMap<(PointKey, PointKey), usize>,
+    pairs: Vec<(Edge, ArrayVec<Point2D<f64>, 2>)>,
+}
+
+impl Pairs {
+    fn insert(&mut self, a: Point2D<f64>, b: Point2D<f64>, opposite: Point2D<f64>) {
+        let key = (PointKey::from(a), PointKey::from(b));
+        let key = if key.0 < key.1 { key } else { (key.1, key.0) };
+
+        let pairs = &mut self.pairs;
+        let i = *self.edges.entry(key).or_insert_with(|| {
+            pairs.push(([a, b], ArrayVec::new()));
+            pairs.len() - 1
+        });
+        // a third triangle on one edge means the input overlaps itself; ignore it
+        let _ = self.pairs[i].1.try_push(opposite);
+    }
+
+    fn complete(self) -> impl Iterator<Item = (Edge, [Point2D<f64>; 2])> {
+        self.pairs
+            .into_iter()
+            .filter_map(|(edge, opposite)| Some((edge, opposite.into_inner().ok()?)))
+    }
+}
+
+impl MatchList {
+    /// Converts the tiling into thick and thin rhombi by way of its half-tiles. Each half-kite is
+    /// cut into an obtuse triangle on its long edge and an acute triangle on its short edge; the
+    /// acute triangles of a kite's two halves form a thin rhombus, and the obtuse triangles either
+    /// side of a long edge, including whole half-darts, form a thick rhombus.
+    ///
+    /// Rhombi which would be completed by tiles outside the list are left out.
+    pub fn rhombi(&self) -> RhombusList {
+        let mut thick = Pairs::default();
+        let mut thin = Pairs::default();
+
+        for half in self.robinson_triangles() {
+            let Triangle { a, b, c } = half.triangle;
+            match half.kind {
+                TriangleKind::Acute => {
+                    let split = a.lerp(b, 1f64 / golden_ratio::<f64>());
+                    thin.insert(b, split, c);
+                    thick.insert(a, c, split);
+                }
+                TriangleKind::Obtuse => thick.insert(b, c, a),
+            }
+        }
+
+        // thin rhombi have their acute corners at the wings of a kite, thick ones at the ends of a
+        // long edge
+        RhombusList {
+            thick: thick
+                .complete()
+                .map(|([from, to], _)| ThickRhombus {
+                    mapping: place(from, to),
+                })
+                .collect(),
+            thin: thin
+                .complete()
+                .map(|(_, [from, to])| ThinRhombus {
+                    mapping: place(from, to),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Box2D;
+
+    use super::*;
+    use crate::{FiveFold, Tiling};
+
+    #[test]
+    fn king() {
+        let bounds = Box2D::new(Point2D::new(-20., -20.), Point2D::new(20., 20.));
+        let tiles = Tiling::new(FiveFold::king_configuration(), bounds).compute_area();
+        let rhombi = tiles.rhombi();
+
+        // every kite holds exactly one thin rhombus, spanning its wings and head
+        assert_eq!(rhombi.thin.len(), tiles.kites.len());
+        for (kite, rhombus) in tiles.kites.iter().zip(rhombi.thin.iter()) {
+            let (kite, rhombus) = (kite.path(), rhombus.path());
+            assert!(kite[1].distance_to(rhombus[0]) < 1e-9);
+            assert!(kite[3].distance_to(rhombus[2]) < 1e-9);
+            assert!(
+                kite[2]
+                    .distance_to(rhombus[1])
+                    .min(kite[2].distance_to(rhombus[3]))
+                    < 1e-9
+            );
+        }
+        assert!(!rhombi.thick.is_empty());
+
+        let paths = rhombi
+            .thick
+            .iter()
+            .map(|rhombus| rhombus.path())
+            .chain(rhombi.thin.iter().map(|rhombus| rhombus.path()))
+            .collect::<Vec<_>>();
+        for path in paths.iter() {
+            for (i, corner) in path.iter().enumerate() {
+                assert!((corner.distance_to(path[(i + 1) % 4]) - 1f64).abs() < 1e-9);
+            }
+
+            let centre = path[0].lerp(path[2], 0.5);
+            let covering = rhombi
+                .thick
+                .iter()
+                .filter(|rhombus| rhombus.contains(centre))
+                .count()
+                + rhombi
+                    .thin
+                    .iter()
+                    .filter(|rhombus| rhombus.contains(centre))
+                    .count();
+            assert_eq!(covering, 1);
+        }
+    }
+}
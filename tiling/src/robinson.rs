@@ -0,0 +1,143 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use euclid::default::Point2D;
+use euclid::Angle;
+
+use super::identity::TileIndex;
+use super::shape::{Shape, Triangle};
+use super::tiling::MatchList;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TriangleKind {
+    /// Half a kite, with a 36 degree apex.
+    Acute,
+    /// Half a dart, with a 108 degree apex.
+    Obtuse,
+}
+
+/// Which side of its axis a half-tile lies on, looking from the apex.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Chirality {
+    Left,
+    Right,
+}
+
+/// Half of a kite or dart, split along its axis. The apex `a` joins the two equal sides, the side
+/// from `a` to `b` runs along the axis, and `c` is the wing.
+#[derive(Debug, Copy, Clone)]
+pub struct RobinsonTriangle {
+    pub kind: TriangleKind,
+    pub chirality: Chirality,
+    /// The direction of the axis from the apex.
+    pub orientation: Angle<f64>,
+    pub triangle: Triangle,
+    pub tile: TileIndex,
+}
+
+fn halves(
+    kind: TriangleKind,
+    tile: TileIndex,
+    apex: Point2D<f64>,
+    axis: Point2D<f64>,
+    path: [Point2D<f64>; 4],
+) -> [RobinsonTriangle; 2] {
+    [path[1], path[3]].map(|wing| {
+        let chirality = if (axis - apex).cross(wing - apex) > 0f64 {
+            Chirality::Left
+        } else {
+            Chirality::Right
+        };
+
+        RobinsonTriangle {
+            kind,
+            chirality,
+            orientation: Angle::radians((axis.y - apex.y).atan2(axis.x - apex.x)).positive(),
+            triangle: Triangle {
+                a: apex,
+                b: axis,
+                c: wing,
+            },
+            tile,
+        }
+    })
+}
+
+impl MatchList {
+    /// Splits every kite into two acute golden triangles and every dart into two obtuse ones.
+    pub fn robinson_triangles(&self) -> impl Iterator<Item = RobinsonTriangle> + '_ {
+        let kites = self.kites.iter().enumerate().flat_map(|(i, kite)| {
+            let path = kite.path();
+            halves(
+                TriangleKind::Acute,
+                TileIndex::Kite(i),
+                path[0],
+                path[2],
+                path,
+            )
+        });
+        // the apex of a half-dart is its notch, leaving the long edge as the base
+        let darts = self.darts.iter().enumerate().flat_map(|(i, dart)| {
+            let path = dart.path();
+            halves(
+                TriangleKind::Obtuse,
+                TileIndex::Dart(i),
+                path[2],
+                path[0],
+                path,
+            )
+        });
+
+        kites.chain(darts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Box2D;
+
+    use super::*;
+    use crate::{FiveFold, Tiling};
+
+    #[test]
+    fn halves() {
+        let bounds = Box2D::new(Point2D::new(-10., -10.), Point2D::new(10., 10.));
+        let tiles = Tiling::new(FiveFold::sun_configuration(), bounds).compute_area();
+        let triangles = tiles.robinson_triangles().collect::<Vec<_>>();
+
+        assert_eq!(triangles.len(), 2 * (tiles.kites.len() + tiles.darts.len()));
+        for pair in triangles.chunks(2) {
+            assert_eq!(pair[0].tile, pair[1].tile);
+            assert_ne!(pair[0].chirality, pair[1].chirality);
+            assert_eq!(pair[0].orientation, pair[1].orientation);
+        }
+
+        for half in triangles.iter() {
+            let Triangle { a, b, c } = half.triangle;
+            let (ab, ac) = (b - a, c - a);
+            let apex = (ab.dot(ac) / (ab.length() * ac.length()))
+                .acos()
+                .to_degrees();
+            match half.kind {
+                TriangleKind::Acute => assert!((apex - 36f64).abs() < 1e-9),
+                TriangleKind::Obtuse => assert!((apex - 108f64).abs() < 1e-9),
+            }
+            assert!((a.distance_to(b) - a.distance_to(c)).abs() < 1e-9);
+        }
+    }
+}
@@ -23,10 +23,11 @@ pub trait Shape<const N: usize> {
     fn path(&self) -> [Point2D<f64>; N];
 }
 
-pub(crate) struct Triangle {
-    pub(crate) a: Point2D<f64>,
-    pub(crate) b: Point2D<f64>,
-    pub(crate) c: Point2D<f64>,
+#[derive(Debug, Copy, Clone)]
+pub struct Triangle {
+    pub a: Point2D<f64>,
+    pub b: Point2D<f64>,
+    pub c: Point2D<f64>,
 }
 
 impl Shape<3> for Triangle {
@@ -16,24 +16,69 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use super::constellation::Constellation;
+use std::collections::BTreeSet;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use super::ammann::{AmmannBar, UndeterminedRegion};
+use super::compute::{Computation, ComputeError, ComputeOptions, ComputeStats, Progress};
+use super::constellation::{Constellation, Forcing};
 use super::dart::Dart;
 use super::double_kite::DoubleKite;
 use super::fivefold::FiveFold;
+use super::hierarchy::Hierarchy;
+use super::identity::{dart_key, kite_key, TileKey};
+use super::intersection_point::{fifth_turns, IntersectionPoint};
 use super::kite::Kite;
-use crate::constants::epsilon;
-use euclid::default::Box2D;
+use super::patch::{self, Occurrence};
+use super::shape::Shape;
+use crate::constants::{epsilon, golden_ratio};
+use crate::musical_sequence::BarBound;
+use euclid::default::{Box2D, Point2D, Vector2D};
+use rustc_hash::FxHashSet;
+use std::f64::consts::TAU;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct MatchList {
     pub kites: Vec<Kite>,
     pub darts: Vec<Dart>,
 }
 
-fn force_new<T: Constellation + Sized>(plane: &mut FiveFold, constellations: &[T]) -> bool {
+fn force_new<T: Constellation + Sized>(
+    plane: &mut FiveFold,
+    constellations: &[T],
+) -> Option<Forcing> {
     constellations
         .iter()
-        .any(|constellation| constellation.force_bars(plane))
+        .find_map(|constellation| constellation.force_bars(plane))
+}
+
+// whether a search of the bounds finds the constellation, which it does if every point of its
+// pattern lies within them
+fn found_in<C: Constellation>(constellation: &C, bounds: &Box2D<f64>) -> bool {
+    let mapping = constellation.mapping();
+    (C::key_pair().iter().chain(C::pattern()))
+        .all(|point| bounds.contains(mapping.transform_point(point.point())))
+}
+
+// the first point of each box, to scan for constellations box by box
+fn boundaries<'a>(points: &BTreeSet<&'a IntersectionPoint>) -> Vec<&'a IntersectionPoint> {
+    let mut boundaries = Vec::new();
+    let mut layer = -1f64;
+    let mut theta = -1f64;
+
+    for point in points.iter() {
+        if (point.box_layer() - layer).abs() > epsilon::<f64>()
+            || (point.box_theta() - theta).abs() > epsilon::<f64>()
+        {
+            layer = point.box_layer();
+            theta = point.box_theta();
+
+            boundaries.push(*point);
+        }
+    }
+
+    boundaries
 }
 
 pub struct Tiling {
@@ -46,50 +91,890 @@ impl Tiling {
         Self { plane, bounds }
     }
 
+    pub fn plane(&self) -> &FiveFold {
+        &self.plane
+    }
+
+    pub fn bounds(&self) -> Box2D<f64> {
+        self.bounds
+    }
+
+    /// Gives access to the plane between computations, e.g. to choose bars with
+    /// [`FiveFold::choose_bar`] before computing the area again.
+    pub fn plane_mut(&mut self) -> &mut FiveFold {
+        &mut self.plane
+    }
+
     pub fn compute_area(&mut self) -> MatchList {
+        self.compute_area_with(ComputeOptions::default())
+            .unwrap_or_else(ComputeError::partial)
+            .matches
+    }
+
+    /// Computes the area as [`Tiling::compute_area`] does, within the limits of the options.
+    pub fn compute_area_with(
+        &mut self,
+        options: ComputeOptions,
+    ) -> Result<Computation, ComputeError> {
+        self.compute_within(options, &|_| true)
+    }
+
+    // computes the area, searching only the intersection points for which `within` holds
+    fn compute_within(
+        &mut self,
+        mut options: ComputeOptions,
+        within: &dyn Fn(Point2D<f64>) -> bool,
+    ) -> Result<Computation, ComputeError> {
+        let start = Instant::now();
         let mut darts = Vec::new();
         let mut double_kites = Vec::new();
         let mut kites = Vec::new();
+        let mut forcings = Vec::new();
+        let mut stats = ComputeStats::default();
 
-        loop {
+        let mut iteration = 0;
+        let stopped: Option<fn(Box<Computation>) -> ComputeError> = loop {
+            if options.max_iterations.is_some_and(|max| iteration >= max) {
+                break Some(ComputeError::Iterations);
+            }
             darts.clear();
             double_kites.clear();
             kites.clear();
 
+            let phase = Instant::now();
+            let cached = self.plane.cache_size();
             self.plane.update_intersection_points(&self.bounds);
+            stats.points_generated += self.plane.cache_size() - cached;
+            let points = {
+                let mut points = self.plane.intersection_points(&self.bounds);
+                points.retain(|point| within(point.point()));
+                let boundaries = boundaries(&points);
+                stats.points_time += phase.elapsed();
+
+                let phase = Instant::now();
+                stats.dart_pairs +=
+                    Dart::constellations(&points, &self.plane, Some(&boundaries), &mut darts);
+                stats.double_kite_pairs += DoubleKite::constellations(
+                    &points,
+                    &self.plane,
+                    Some(&boundaries),
+                    &mut double_kites,
+                );
+                stats.kite_pairs +=
+                    Kite::constellations(&points, &self.plane, Some(&boundaries), &mut kites);
+                stats.search_time += phase.elapsed();
+                points.len()
+            };
+
+            // points near the edge of a box are duplicated into its neighbours, and so are their
+            // tiles
+            let mut seen = FxHashSet::default();
+            kites.retain(|kite| seen.insert(kite_key(kite)));
+            seen.clear();
+            darts.retain(|dart| seen.insert(dart_key(dart)));
+            seen.clear();
+            double_kites.retain(|double_kite| seen.insert(TileKey::from(&double_kite.mapping())));
+
+            iteration += 1;
+            if let Some(progress) = options.progress.as_mut() {
+                progress(Progress {
+                    iteration,
+                    points,
+                    tiles: kites.len() + darts.len(),
+                });
+            }
+
+            let phase = Instant::now();
+            let forcing = force_new(&mut self.plane, &darts)
+                .or_else(|| force_new(&mut self.plane, &double_kites));
+            stats.forcing_time += phase.elapsed();
+            match forcing {
+                Some(forcing) => forcings.push(forcing),
+                None => break None,
+            }
+
+            if options
+                .cancel
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+            {
+                break Some(ComputeError::Cancelled);
+            }
+            if options
+                .time_budget
+                .is_some_and(|budget| start.elapsed() >= budget)
             {
-                let points = self.plane.intersection_points(&self.bounds);
+                break Some(ComputeError::TimedOut);
+            }
+        };
+
+        stats.iterations = iteration;
+        stats.cache_size = self.plane.cache_size();
+        stats.forcings = forcings.len();
+        stats.total_time = start.elapsed();
+        let computation = Computation {
+            matches: MatchList { kites, darts },
+            double_kites,
+            forcings,
+            stats,
+        };
+        match stopped {
+            Some(error) => Err(error(Box::new(computation))),
+            None => Ok(computation),
+        }
+    }
+
+    /// Computes the area as [`Tiling::compute_area_with`] does, for a plane which is rotationally
+    /// symmetric, by searching only the fifth of it which turns onto the rest and turning copies of
+    /// the tiles found there into place. The tiles either side of each seam between copies are
+    /// checked to fit together. Planes which aren't symmetric are computed directly.
+    ///
+    /// The forcings and statistics are those of the one fifth computed.
+    pub fn compute_area_symmetric(
+        &mut self,
+        options: ComputeOptions,
+    ) -> Result<Computation, ComputeError> {
+        if !self.plane.is_rotationally_symmetric() {
+            return self.compute_area_with(options);
+        }
+
+        let bounds = self.bounds;
+        let radius = [
+            bounds.min,
+            bounds.max,
+            Point2D::new(bounds.min.x, bounds.max.y),
+            Point2D::new(bounds.max.x, bounds.min.y),
+        ]
+        .iter()
+        .map(|corner| corner.to_vector().length())
+        .fold(0f64, f64::max);
+        // enough room around the sector for the patterns of the tiles centred in it to be found
+        let margin = 2f64 * golden_ratio::<f64>();
+        let sector = TAU / 5f64;
+        let seam_distance = |point: Point2D<f64>, turns: usize| {
+            let (sin, cos) = (turns as f64 * sector).sin_cos();
+            let along = (point.x * cos + point.y * sin).max(0f64);
+            point.distance_to(Point2D::new(along * cos, along * sin))
+        };
+        let in_sector = |point: Point2D<f64>| {
+            let angle = point.y.atan2(point.x).rem_euclid(TAU);
+            let angle = if angle > TAU - epsilon::<f64>() {
+                angle - TAU
+            } else {
+                angle
+            };
+            angle > -epsilon::<f64>() && angle < sector - epsilon::<f64>()
+        };
+        // the points near the sector which some turn brings into the area
+        let within = |point: Point2D<f64>| {
+            (in_sector(point) || seam_distance(point, 0).min(seam_distance(point, 1)) < margin)
+                && (0..5).any(|turns| bounds.contains(fifth_turns(turns).transform_point(point)))
+        };
+
+        self.bounds = Box2D::new(
+            Point2D::new(-margin, -margin),
+            Point2D::new(radius + margin, radius * sector.sin() + margin),
+        );
+        let computed = self.compute_within(options, &within);
+        self.bounds = bounds;
+
+        let sequences = self.plane.sequences();
+        let centre = |path: [Point2D<f64>; 4]| {
+            (path
+                .iter()
+                .fold(Vector2D::zero(), |sum, corner| sum + corner.to_vector())
+                / 4f64)
+                .to_point()
+        };
+        // the copies the search of the whole area would have found
+        let turned = |computed: Computation| {
+            let mut symmetric = Computation {
+                forcings: computed.forcings,
+                stats: computed.stats,
+                ..Default::default()
+            };
+            for turns in 0..5 {
+                symmetric.matches.kites.extend(
+                    (computed.matches.kites.iter())
+                        .filter(|kite| in_sector(centre(kite.path())))
+                        .map(|kite| kite.rotated(turns))
+                        .filter(|kite| found_in(kite, &bounds)),
+                );
+                symmetric.matches.darts.extend(
+                    (computed.matches.darts.iter())
+                        .filter(|dart| in_sector(centre(dart.path())))
+                        .map(|dart| dart.rotated(turns, sequences))
+                        .filter(|dart| found_in(dart, &bounds)),
+                );
+                symmetric.double_kites.extend(
+                    (computed.double_kites.iter())
+                        .filter(|double_kite| {
+                            in_sector(double_kite.mapping().transform_point(Point2D::origin()))
+                        })
+                        .map(|double_kite| double_kite.rotated(turns))
+                        .filter(|double_kite| found_in(double_kite, &bounds)),
+                );
+            }
+
+            // those centred on the origin are all in the sector, and turn onto each other
+            let mut seen = FxHashSet::default();
+            symmetric
+                .matches
+                .kites
+                .retain(|kite| seen.insert(kite_key(kite)));
+            seen.clear();
+            symmetric
+                .matches
+                .darts
+                .retain(|dart| seen.insert(dart_key(dart)));
+            seen.clear();
+            (symmetric.double_kites)
+                .retain(|double_kite| seen.insert(TileKey::from(&double_kite.mapping())));
+            symmetric
+        };
+        let symmetric = computed
+            .map(turned)
+            .map_err(|error| error.map_partial(turned))?;
+
+        // the tiles along the seams, which must fit together as well as any others
+        let near_seam = |centre| (0..5).any(|turns| seam_distance(centre, turns) < margin);
+        let seams = MatchList {
+            kites: (symmetric.matches.kites.iter())
+                .filter(|kite| near_seam(centre(kite.path())))
+                .copied()
+                .collect(),
+            darts: (symmetric.matches.darts.iter())
+                .filter(|dart| near_seam(centre(dart.path())))
+                .cloned()
+                .collect(),
+        };
+        match seams.verify() {
+            Ok(()) => Ok(symmetric),
+            Err(violations) => Err(ComputeError::Seams(Box::new(symmetric), violations)),
+        }
+    }
 
-                let mut boundaries = Vec::new();
-                let mut layer = -1f64;
-                let mut theta = -1f64;
+    /// Finds every instance of a constellation among the intersection points of the area as the
+    /// plane is currently forced, without forcing anything further.
+    pub fn constellations<C: Constellation>(&mut self) -> Vec<C> {
+        self.plane.update_intersection_points(&self.bounds);
+        let points = self.plane.intersection_points(&self.bounds);
 
-                for point in points.iter() {
-                    if (point.box_layer() - layer).abs() > epsilon::<f64>()
-                        || (point.box_theta() - theta).abs() > epsilon::<f64>()
-                    {
-                        layer = point.box_layer();
-                        theta = point.box_theta();
+        let mut found = Vec::new();
+        C::constellations(&points, &self.plane, Some(&boundaries(&points)), &mut found);
 
-                        boundaries.push(*point);
-                    }
+        // points near the edge of a box are duplicated into its neighbours, and so are their
+        // constellations
+        let mut seen = FxHashSet::default();
+        found.retain(|constellation: &C| seen.insert(TileKey::from(&constellation.mapping())));
+        found
+    }
+
+    /// Finds the placement of a patch nearest to a point, searching a box around the point which
+    /// grows until it holds a placement no other could be nearer than, or it would grow beyond
+    /// `max_radius` of the point. In the latter case, the nearest placement found so far is
+    /// returned, if any. The search works on a copy of the plane, so neither the plane nor the
+    /// area is changed.
+    pub fn nearest_occurrence(
+        &self,
+        patch: &MatchList,
+        point: Point2D<f64>,
+        reflections: bool,
+        max_radius: f64,
+    ) -> Option<Occurrence> {
+        let centre = patch::centre(patch);
+        // placements poking out of the area can't be seen, and neither can tiles on its edge
+        let margin = patch::extent(patch) + golden_ratio::<f64>();
+
+        let mut radius = margin.max(1f64);
+        let mut around = Tiling::new(self.plane.clone(), self.bounds);
+        loop {
+            around.bounds = Box2D::new(
+                point - Vector2D::splat(radius),
+                point + Vector2D::splat(radius),
+            );
+
+            let nearest = around
+                .compute_area()
+                .occurrences(patch, reflections)
+                .into_iter()
+                .map(|transform| Occurrence {
+                    transform,
+                    distance: transform.transform_point(centre).distance_to(point),
+                })
+                .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+
+            match nearest {
+                Some(nearest) if nearest.distance + margin <= radius => return Some(nearest),
+                _ if radius >= max_radius => return nearest,
+                _ => radius = (radius * 2f64).min(max_radius),
+            }
+        }
+    }
+
+    /// Lists the regions of the area bounded by bars which aren't forced yet, and so where the
+    /// tiling isn't decided yet either.
+    pub fn undetermined_regions(&self) -> Vec<UndeterminedRegion> {
+        self.plane.undetermined_in(&self.bounds)
+    }
+
+    /// Enumerates every distinct set of tiles the area could end up with, by trying both bounds
+    /// of each bar still unforced within it. Choices which leave tiles overlapping or meeting
+    /// illegally, or a tile needing a bar where none can be, are abandoned. The plane is left as
+    /// it was found.
+    pub fn completions(&mut self) -> Vec<MatchList> {
+        let snapshot = self.plane.snapshot();
+        let mut seen = FxHashSet::default();
+        let mut found = Vec::new();
+        self.complete(&mut seen, &mut found);
+        self.plane.rollback(&snapshot);
+        found
+    }
+
+    /// Composes `tiles`, computed from this tiling, into supertiles as [`MatchList::hierarchy`]
+    /// does, after filling the gaps left by unforced bars with the first completion of the area
+    /// found. Tiles beside a gap then find their supertiles too, those of the completion chosen
+    /// where completions differ. The levels above `tiles` are composed from the completion, and
+    /// the plane is left as it was found.
+    pub fn hierarchy(&mut self, tiles: &MatchList, depth: usize) -> Hierarchy {
+        let snapshot = self.plane.snapshot();
+        let completion = self.complete_first();
+        self.plane.rollback(&snapshot);
+        match completion {
+            Some(completion) => Hierarchy::new(&completion, depth).reindexed(&completion, tiles),
+            None => Hierarchy::new(tiles, depth),
+        }
+    }
+
+    // computes the area, giving up on it if its tiles can no longer be completed
+    fn compute_legal(&mut self) -> Option<MatchList> {
+        let computed = self
+            .compute_area_with(ComputeOptions::default())
+            .unwrap_or_else(ComputeError::partial);
+        let contradicted = (computed.matches.darts.iter())
+            .any(|dart| dart.contradicts(&self.plane))
+            || (computed.double_kites.iter())
+                .any(|double_kite| double_kite.contradicts(&self.plane));
+        // the tiles found so far stay as more bars are forced, so a branch gone wrong stays wrong
+        if contradicted || computed.matches.verify().is_err() {
+            return None;
+        }
+        Some(computed.matches)
+    }
+
+    fn open_bar(&self) -> Option<AmmannBar> {
+        (self.plane.bars_in(&self.bounds).into_iter()).find(|bar| !bar.forced)
+    }
+
+    fn complete(
+        &mut self,
+        seen: &mut FxHashSet<(Vec<TileKey>, Vec<TileKey>)>,
+        found: &mut Vec<MatchList>,
+    ) {
+        let matches = match self.compute_legal() {
+            Some(matches) => matches,
+            None => return,
+        };
+
+        match self.open_bar() {
+            None => {
+                let mut kites = matches.kites.iter().map(kite_key).collect::<Vec<_>>();
+                let mut darts = matches.darts.iter().map(dart_key).collect::<Vec<_>>();
+                kites.sort_unstable();
+                darts.sort_unstable();
+                if seen.insert((kites, darts)) {
+                    found.push(matches);
+                }
+            }
+            Some(open) => {
+                let snapshot = self.plane.snapshot();
+                for bound in [BarBound::Longer, BarBound::Shorter] {
+                    self.plane
+                        .choose_bar(open.sequence, open.bar, bound)
+                        .expect("Bar is unforced");
+                    self.complete(seen, found);
+                    self.plane.rollback(&snapshot);
                 }
+            }
+        }
+    }
 
-                Dart::constellations(&points, &self.plane, Some(&boundaries), &mut darts);
-                DoubleKite::constellations(
-                    &points,
-                    &self.plane,
-                    Some(&boundaries),
-                    &mut double_kites,
+    // forces the open bars as `complete` does, stopping at the first completion; the plane is
+    // left forced as it was completed
+    fn complete_first(&mut self) -> Option<MatchList> {
+        let matches = self.compute_legal()?;
+        let open = match self.open_bar() {
+            Some(open) => open,
+            None => return Some(matches),
+        };
+
+        let snapshot = self.plane.snapshot();
+        for bound in [BarBound::Longer, BarBound::Shorter] {
+            self.plane
+                .choose_bar(open.sequence, open.bar, bound)
+                .expect("Bar is unforced");
+            if let Some(completion) = self.complete_first() {
+                return Some(completion);
+            }
+            self.plane.rollback(&snapshot);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    use euclid::default::{Point2D, Transform2D};
+    use lazy_static::lazy_static;
+
+    use super::*;
+
+    #[test]
+    fn completions() {
+        let bounds = Box2D::new(Point2D::new(-3f64, -3f64), Point2D::new(3f64, 3f64));
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        let forced = |tiling: &Tiling| {
+            (tiling.plane().bars_in(&bounds).into_iter())
+                .map(|bar| bar.forced)
+                .collect::<Vec<_>>()
+        };
+        let before = forced(&tiling);
+
+        let completions = tiling.completions();
+        assert!(completions.len() > 1);
+        assert_eq!(forced(&tiling), before);
+
+        let keys = completions
+            .iter()
+            .map(|matches| {
+                let mut keys = matches.kites.iter().map(kite_key).collect::<Vec<_>>();
+                keys.sort_unstable();
+                keys
+            })
+            .collect::<FxHashSet<_>>();
+        assert!(keys.len() > 1);
+    }
+
+    #[test]
+    fn completions_are_legal() {
+        let bounds = Box2D::new(Point2D::new(-4f64, -4f64), Point2D::new(4f64, 4f64));
+        for plane in [
+            FiveFold::sun_configuration(),
+            FiveFold::king_configuration(),
+        ] {
+            let completions = Tiling::new(plane, bounds).completions();
+            assert!(!completions.is_empty());
+            for matches in completions.iter() {
+                assert_eq!(matches.verify(), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn forcing_between_computations() {
+        let bounds = Box2D::new(Point2D::new(-5f64, -5f64), Point2D::new(5f64, 5f64));
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+
+        // imported tiles carry no optional points to force
+        let (dart, _) =
+            Dart::from_vertices(&crate::fixtures::sun_patch(1).darts[0].path()).unwrap();
+        assert!(dart.force_bars(tiling.plane_mut()).is_none());
+
+        let sequences = tiling.plane().sequences()[..2].to_vec();
+        for ms in sequences.iter() {
+            tiling.plane_mut().force_point(Point2D::new(0.3, 0.7), ms);
+        }
+        assert_eq!(tiling.compute_area().verify(), Ok(()));
+    }
+
+    #[test]
+    fn forcings() {
+        let bounds = Box2D::new(Point2D::new(-10f64, -10f64), Point2D::new(10f64, 10f64));
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        let computed = tiling.compute_area_with(ComputeOptions::default()).unwrap();
+        assert!(!computed.forcings.is_empty());
+
+        let mut seen = FxHashSet::default();
+        for forcing in computed.forcings.iter() {
+            assert!(seen.insert((forcing.sequence, forcing.bar)));
+            assert!(tiling.plane().sequences()[forcing.sequence].is_forced(forcing.bar));
+        }
+    }
+
+    #[test]
+    fn compute_options() {
+        let bounds = Box2D::new(Point2D::new(-10f64, -10f64), Point2D::new(10f64, 10f64));
+        let full = Tiling::new(FiveFold::king_configuration(), bounds)
+            .compute_area_with(ComputeOptions::default())
+            .unwrap();
+
+        let mut reports = Vec::new();
+        let computed = Tiling::new(FiveFold::king_configuration(), bounds)
+            .compute_area_with(ComputeOptions {
+                progress: Some(Box::new(|progress| reports.push(progress))),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(computed.matches.kites.len(), full.matches.kites.len());
+        assert_eq!(reports.len(), full.forcings.len() + 1);
+        assert!(reports
+            .iter()
+            .map(|report| report.iteration)
+            .eq(1..=reports.len()));
+        assert!(reports.iter().all(|report| report.points > 0));
+        let last = reports.last().unwrap();
+        assert_eq!(
+            last.tiles,
+            computed.matches.kites.len() + computed.matches.darts.len()
+        );
+
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        let partial = tiling
+            .compute_area_with(ComputeOptions {
+                max_iterations: Some(2),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(matches!(partial, ComputeError::Iterations(_)));
+        assert_eq!(partial.partial().forcings.len(), 2);
+        // picking up where it stopped gets the rest
+        let rest = tiling.compute_area_with(ComputeOptions::default()).unwrap();
+        assert_eq!(rest.forcings.len(), full.forcings.len() - 2);
+        assert_eq!(rest.matches.kites.len(), full.matches.kites.len());
+
+        // no searches at all leaves the plane alone
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        let untouched = tiling
+            .compute_area_with(ComputeOptions {
+                max_iterations: Some(0),
+                ..Default::default()
+            })
+            .unwrap_err()
+            .partial();
+        assert!(untouched.matches.kites.is_empty() && untouched.forcings.is_empty());
+        assert_eq!(untouched.stats.iterations, 0);
+        let rest = tiling.compute_area_with(ComputeOptions::default()).unwrap();
+        assert_eq!(rest.forcings.len(), full.forcings.len());
+
+        let cancel = AtomicBool::new(true);
+        let cancelled = Tiling::new(FiveFold::king_configuration(), bounds)
+            .compute_area_with(ComputeOptions {
+                cancel: Some(&cancel),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(
+            matches!(cancelled, ComputeError::Cancelled(ref partial) if partial.forcings.len() == 1)
+        );
+
+        let timed_out = Tiling::new(FiveFold::king_configuration(), bounds)
+            .compute_area_with(ComputeOptions {
+                time_budget: Some(Duration::from_secs(0)),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(matches!(timed_out, ComputeError::TimedOut(_)));
+    }
+
+    #[test]
+    fn stats() {
+        let bounds = Box2D::new(Point2D::new(-10f64, -10f64), Point2D::new(10f64, 10f64));
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        let stats = tiling
+            .compute_area_with(ComputeOptions::default())
+            .unwrap()
+            .stats;
+
+        assert_eq!(stats.iterations, stats.forcings + 1);
+        assert_eq!(stats.points_generated, stats.cache_size);
+        assert!(stats.dart_pairs > 0 && stats.double_kite_pairs > 0 && stats.kite_pairs > 0);
+        assert!(stats.points_time + stats.search_time + stats.forcing_time <= stats.total_time);
+
+        // everything is cached and forced already the second time around
+        let again = tiling
+            .compute_area_with(ComputeOptions::default())
+            .unwrap()
+            .stats;
+        assert_eq!((again.iterations, again.forcings), (1, 0));
+        assert_eq!(again.points_generated, 0);
+        assert_eq!(again.cache_size, stats.cache_size);
+    }
+
+    #[test]
+    fn compute_area_symmetric() {
+        assert!(FiveFold::sun_configuration().is_rotationally_symmetric());
+        assert!(FiveFold::star_configuration().is_rotationally_symmetric());
+        assert!(!FiveFold::king_configuration().is_rotationally_symmetric());
+
+        // exactly the tiles of a direct computation, out to the edges of the area
+        let keys = |computed: &Computation| {
+            let mut kites = computed
+                .matches
+                .kites
+                .iter()
+                .map(kite_key)
+                .collect::<Vec<_>>();
+            let mut darts = computed
+                .matches
+                .darts
+                .iter()
+                .map(dart_key)
+                .collect::<Vec<_>>();
+            let mut double_kites = (computed.double_kites.iter())
+                .map(|double_kite| TileKey::from(&double_kite.mapping()))
+                .collect::<Vec<_>>();
+            kites.sort();
+            darts.sort();
+            double_kites.sort();
+            (kites, darts, double_kites)
+        };
+        let areas = [
+            (FiveFold::star_configuration(), Point2D::new(40f64, 20f64)),
+            (FiveFold::sun_configuration(), Point2D::new(15f64, 25f64)),
+        ];
+        for (plane, corner) in areas {
+            let bounds = Box2D::new(-corner, corner);
+            let symmetric = Tiling::new(plane.clone(), bounds)
+                .compute_area_symmetric(ComputeOptions::default())
+                .unwrap();
+            let direct = Tiling::new(plane, bounds)
+                .compute_area_with(ComputeOptions::default())
+                .unwrap();
+            assert!(!direct.matches.kites.is_empty());
+            assert_eq!(keys(&symmetric), keys(&direct));
+            assert_eq!(symmetric.matches.verify(), Ok(()));
+        }
+
+        // stopping early still turns what was found into place
+        let bounds = Box2D::new(Point2D::new(-10f64, -10f64), Point2D::new(10f64, 10f64));
+        let stopped = Tiling::new(FiveFold::star_configuration(), bounds)
+            .compute_area_symmetric(ComputeOptions {
+                max_iterations: Some(1),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(matches!(stopped, ComputeError::Iterations(_)));
+        assert!(!stopped.partial().matches.kites.is_empty());
+
+        let symmetric = Tiling::new(FiveFold::star_configuration(), bounds)
+            .compute_area_symmetric(ComputeOptions::default())
+            .unwrap()
+            .matches;
+
+        // each rotated copy keeps the crossings it was found on, turned with it
+        let plane = FiveFold::star_configuration();
+        for kite in symmetric.kites.iter() {
+            for crossing in kite.crossings().unwrap().iter() {
+                for (&sequence, &bar) in crossing.sequences.iter().zip(crossing.bars.iter()) {
+                    let ms = &plane.sequences()[sequence];
+                    let (sin, cos) = ms.rotation().sin_cos();
+                    let along = (crossing.point.x - ms.center_x()) * cos
+                        + (crossing.point.y - ms.center_y()) * sin;
+                    assert!((along - ms.get_bar_distance(bar)).abs() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn double_kites() {
+        let bounds = Box2D::new(Point2D::new(-10f64, -10f64), Point2D::new(10f64, 10f64));
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        let computed = tiling.compute_area_with(ComputeOptions::default()).unwrap();
+        assert!(!computed.double_kites.is_empty());
+
+        let mut seen = FxHashSet::default();
+        for double_kite in computed.double_kites.iter() {
+            assert!(seen.insert(TileKey::from(&double_kite.mapping())));
+            for point in DoubleKite::pattern() {
+                let point = double_kite.mapping().transform_point(point.point());
+                assert!(tiling.plane().intersection_point(point).is_some());
+            }
+        }
+
+        // every dart has at least one of its optional points
+        for dart in computed.matches.darts.iter() {
+            assert!(dart.left().is_some() || dart.right().is_some());
+        }
+    }
+
+    #[test]
+    fn crossings() {
+        let bounds = Box2D::new(Point2D::new(-10f64, -10f64), Point2D::new(10f64, 10f64));
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        let matches = tiling.compute_area();
+
+        let crossings = (matches.kites.iter())
+            .map(|kite| (kite.mapping(), Kite::pattern(), kite.crossings()))
+            .chain(
+                (matches.darts.iter())
+                    .map(|dart| (dart.mapping(), Dart::pattern(), dart.crossings())),
+            );
+        for (mapping, pattern, crossings) in crossings {
+            for (point, crossing) in pattern.iter().zip(crossings.unwrap().iter()) {
+                assert!(
+                    mapping
+                        .transform_point(point.point())
+                        .distance_to(crossing.point)
+                        < 1e-9
                 );
-                Kite::constellations(&points, &self.plane, Some(&boundaries), &mut kites);
+
+                // the crossing lies on both of its bars
+                for (&sequence, &bar) in crossing.sequences.iter().zip(crossing.bars.iter()) {
+                    let ms = &tiling.plane().sequences()[sequence];
+                    let (sin, cos) = ms.rotation().sin_cos();
+                    let along = (crossing.point.x - ms.center_x()) * cos
+                        + (crossing.point.y - ms.center_y()) * sin;
+                    assert!((along - ms.get_bar_distance(bar)).abs() < 1e-9);
+                }
             }
+        }
+        assert!(crate::fixtures::sun().kites[0].crossings().is_none());
+    }
+
+    #[test]
+    fn undetermined_regions() {
+        let bounds = Box2D::new(Point2D::new(-5f64, -5f64), Point2D::new(5f64, 5f64));
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        tiling.compute_area();
 
-            if !(force_new(&mut self.plane, &darts) || force_new(&mut self.plane, &double_kites)) {
-                break;
+        let regions = tiling.undetermined_regions();
+        assert!(!regions.is_empty());
+        for bar in tiling.plane().bars_in(&bounds) {
+            let region = regions
+                .iter()
+                .find(|region| region.sequence == bar.sequence && region.bars.contains(&bar.bar));
+            assert_eq!(region.is_some(), !bar.forced);
+
+            // the bar as currently placed lies within its region
+            if let Some(region) = region {
+                let middle = bar.start.lerp(bar.end, 0.5);
+                let n = region.polygon.len();
+                assert!((0..n).all(|i| {
+                    let (a, b) = (region.polygon[i], region.polygon[(i + 1) % n]);
+                    (b - a).cross(middle - a) >= -1e-9
+                }));
+                let inflated = bounds.inflate(1e-9, 1e-9);
+                assert!(region.polygon.iter().all(|&point| inflated.contains(point)));
             }
         }
 
-        MatchList { kites, darts }
+        for bar in tiling.plane().bars_in(&bounds) {
+            let _ = tiling
+                .plane_mut()
+                .choose_bar(bar.sequence, bar.bar, BarBound::Longer);
+        }
+        assert!(tiling.undetermined_regions().is_empty());
+    }
+
+    // the kite, as it could be detected from outside the crate
+    struct Apex(Transform2D<f64>);
+
+    lazy_static! {
+        static ref PATTERN: [IntersectionPoint; 3] = {
+            let plane = crate::FiveFold::sun_configuration();
+            let sequences = plane.sequences();
+            [
+                crate::intersection_point(&sequences[0], 0, &sequences[1], 0),
+                crate::intersection_point(&sequences[0], 0, &sequences[4], 0),
+                crate::intersection_point(&sequences[1], 0, &sequences[4], 0),
+            ]
+        };
+        static ref KEY_PAIR: [IntersectionPoint; 2] = [PATTERN[0].clone(), PATTERN[1].clone()];
+    }
+
+    impl crate::Constellation for Apex {
+        fn delta() -> f64 {
+            KEY_PAIR[0].point().distance_to(KEY_PAIR[1].point())
+        }
+
+        fn key_pair() -> &'static [IntersectionPoint; 2] {
+            &KEY_PAIR
+        }
+
+        fn pattern() -> &'static [IntersectionPoint] {
+            &*PATTERN
+        }
+
+        fn test_pair(
+            points: &BTreeSet<&IntersectionPoint>,
+            plane: &crate::FiveFold,
+            pair: [&IntersectionPoint; 2],
+        ) -> Option<Self> {
+            crate::test_required(points, plane, pair, Self::key_pair(), Self::pattern()).map(Apex)
+        }
+
+        fn mapping(&self) -> Transform2D<f64> {
+            self.0
+        }
+
+        fn force_bars(&self, _: &mut crate::FiveFold) -> Option<crate::Forcing> {
+            None
+        }
+    }
+
+    #[test]
+    fn custom_constellations() {
+        let bounds = Box2D::new(Point2D::new(-10f64, -10f64), Point2D::new(10f64, 10f64));
+        let mut tiling = Tiling::new(FiveFold::king_configuration(), bounds);
+        let matches = tiling.compute_area();
+
+        let apexes = tiling.constellations::<Apex>();
+        assert_eq!(apexes.len(), matches.kites.len());
+        let kites = matches.kites.iter().map(kite_key).collect::<FxHashSet<_>>();
+        assert!(apexes
+            .iter()
+            .all(|apex| kites.contains(&TileKey::from(&apex.mapping()))));
+    }
+
+    #[test]
+    fn nearest_occurrence() {
+        let plane = FiveFold::king_configuration();
+        let whole = Box2D::new(Point2D::new(-30f64, -30f64), Point2D::new(30f64, 30f64));
+        let all = Tiling::new(plane.clone(), whole).compute_area();
+
+        // the tiles near the middle
+        let near = |mapping: Transform2D<f64>| {
+            let origin = mapping.transform_point(Point2D::origin());
+            origin.to_vector().length() < 2f64
+        };
+        let patch = MatchList {
+            kites: all
+                .kites
+                .iter()
+                .filter(|kite| near(kite.mapping()))
+                .copied()
+                .collect(),
+            darts: all
+                .darts
+                .iter()
+                .filter(|dart| near(dart.mapping()))
+                .cloned()
+                .collect(),
+        };
+
+        let point = Point2D::new(12f64, 5f64);
+        let tiny = Box2D::new(Point2D::new(-1f64, -1f64), Point2D::new(1f64, 1f64));
+        let tiling = Tiling::new(plane.clone(), tiny);
+        let found = tiling
+            .nearest_occurrence(&patch, point, false, 25f64)
+            .unwrap();
+        assert_eq!(tiling.bounds(), tiny);
+        let forced = |plane: &FiveFold| {
+            (plane.bars_in(&whole).into_iter())
+                .map(|bar| bar.forced)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(forced(tiling.plane()), forced(&plane));
+
+        let centre = patch::centre(&patch);
+        let expected = all
+            .occurrences(&patch, false)
+            .iter()
+            .map(|transform| transform.transform_point(centre).distance_to(point))
+            .fold(f64::INFINITY, f64::min);
+        assert!((found.distance - expected).abs() < 1e-6);
     }
 }
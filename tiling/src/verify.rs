@@ -0,0 +1,316 @@
+/*
+ * Penrose: Penrose tiling generation, adjacency, and other miscellaneous APIs.
+ * Copyright (C) 2021  TAMUctf
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use euclid::default::{Point2D, Vector2D};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::constants::golden_ratio;
+use super::identity::{PointKey, TileIndex};
+use super::shape::Shape;
+use super::tiling::MatchList;
+
+// how close points must be to count as touching
+const TOLERANCE: f64 = 1e-6;
+
+// Conway's colouring of the corners of each tile's path; legal tilings only join like colours
+const KITE_COLOURS: [bool; 4] = [true, false, true, false];
+const DART_COLOURS: [bool; 4] = [false, true, false, true];
+
+/// A way in which a set of tiles fails to be a Penrose tiling.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// Two tiles cover some of the same area.
+    Overlap(TileIndex, TileIndex),
+    /// A corner of one tile lies along an edge of another, rather than at its ends.
+    Edge(Point2D<f64>),
+    /// A hole surrounded by tiles, by the corners around it.
+    Gap(Vec<Point2D<f64>>),
+    /// Tiles meeting at a vertex disagree on its colour, breaking the matching rules.
+    Colour(Point2D<f64>),
+}
+
+type Path = [Point2D<f64>; 4];
+
+fn cross(a: Point2D<f64>, b: Point2D<f64>, c: Point2D<f64>) -> f64 {
+    (b - a).cross(c - a)
+}
+
+fn area(path: &[Point2D<f64>]) -> f64 {
+    (0..path.len())
+        .map(|i| {
+            path[i]
+                .to_vector()
+                .cross(path[(i + 1) % path.len()].to_vector())
+        })
+        .sum::<f64>()
+        / 2f64
+}
+
+fn distance_to_edge(a: Point2D<f64>, b: Point2D<f64>, point: Point2D<f64>) -> f64 {
+    let along = b - a;
+    let t = ((point - a).dot(along) / along.square_length()).clamp(0f64, 1f64);
+    a.lerp(b, t).distance_to(point)
+}
+
+fn edges(path: &Path) -> impl Iterator<Item = (Point2D<f64>, Point2D<f64>)> + '_ {
+    (0..4).map(move |i| (path[i], path[(i + 1) % 4]))
+}
+
+// inside the path and clear of its edges
+fn strictly_inside(path: &Path, point: Point2D<f64>) -> bool {
+    let mut inside = false;
+    for (a, b) in edges(path) {
+        if distance_to_edge(a, b, point) < TOLERANCE {
+            return false;
+        }
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (b.x - a.x) * (point.y - a.y) / (b.y - a.y)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// edges crossing at a point inside both of them
+fn crossing(a: Point2D<f64>, b: Point2D<f64>, c: Point2D<f64>, d: Point2D<f64>) -> bool {
+    let (d1, d2) = (cross(a, b, c), cross(a, b, d));
+    let (d3, d4) = (cross(c, d, a), cross(c, d, b));
+    d1 * d2 < -TOLERANCE && d3 * d4 < -TOLERANCE
+}
+
+fn overlaps(a: &Path, b: &Path) -> bool {
+    let middle = |path: &Path| {
+        path.iter().fold(Point2D::origin(), |sum, point| {
+            sum + point.to_vector() / 4f64
+        })
+    };
+
+    a.iter().any(|&point| strictly_inside(b, point))
+        || b.iter().any(|&point| strictly_inside(a, point))
+        || strictly_inside(b, middle(a))
+        || strictly_inside(a, middle(b))
+        || edges(a).any(|(p, q)| edges(b).any(|(r, s)| crossing(p, q, r, s)))
+}
+
+// a corner of `a` along an edge of `b`, but not at either end of it
+fn t_junction(a: &Path, b: &Path) -> Option<Point2D<f64>> {
+    a.iter().copied().find(|&point| {
+        edges(b).any(|(p, q)| {
+            distance_to_edge(p, q, point) < TOLERANCE
+                && p.distance_to(point) > TOLERANCE
+                && q.distance_to(point) > TOLERANCE
+        })
+    })
+}
+
+impl MatchList {
+    /// Checks that the tiles form a legal Penrose tiling of the area they cover: that no two
+    /// overlap, that they meet edge to edge, that they leave no holes, and that they follow the
+    /// matching rules, as given by Conway's colouring of their corners.
+    pub fn verify(&self) -> Result<(), Vec<Violation>> {
+        let tiles = (self.kites.iter().enumerate())
+            .map(|(i, kite)| (TileIndex::Kite(i), kite.path(), KITE_COLOURS))
+            .chain(
+                (self.darts.iter().enumerate())
+                    .map(|(i, dart)| (TileIndex::Dart(i), dart.path(), DART_COLOURS)),
+            )
+            .collect::<Vec<_>>();
+        let mut violations = Vec::new();
+
+        // tiles reach at most the golden ratio from their first corner, so only neighbouring
+        // cells of this size can hold tiles touching each other
+        let cell = 2f64 * golden_ratio::<f64>();
+        let mut grid = FxHashMap::<(i64, i64), Vec<usize>>::default();
+        for (i, (_, path, _)) in tiles.iter().enumerate() {
+            let cell = (
+                (path[0].x / cell).floor() as i64,
+                (path[0].y / cell).floor() as i64,
+            );
+            grid.entry(cell).or_default().push(i);
+        }
+
+        let mut junctions = FxHashSet::default();
+        for (&(x, y), members) in grid.iter() {
+            for &i in members.iter() {
+                let neighbours = (-1..=1)
+                    .flat_map(|dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+                    .filter_map(|cell| grid.get(&cell))
+                    .flatten()
+                    .filter(|&&j| j > i);
+                for &j in neighbours {
+                    let (a, b) = (&tiles[i], &tiles[j]);
+                    if overlaps(&a.1, &b.1) {
+                        violations.push(Violation::Overlap(a.0, b.0));
+                    }
+                    for point in t_junction(&a.1, &b.1)
+                        .into_iter()
+                        .chain(t_junction(&b.1, &a.1))
+                    {
+                        if junctions.insert(PointKey::from(point)) {
+                            violations.push(Violation::Edge(point));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut colours = FxHashMap::default();
+        let mut miscoloured = FxHashSet::default();
+        for (_, path, tile_colours) in tiles.iter() {
+            for (&point, &colour) in path.iter().zip(tile_colours.iter()) {
+                let key = PointKey::from(point);
+                if *colours.entry(key).or_insert(colour) != colour && miscoloured.insert(key) {
+                    violations.push(Violation::Colour(point));
+                }
+            }
+        }
+
+        // the edges of each tile anticlockwise, so holes are the loops of unshared edges going
+        // the other way
+        let mut directed = FxHashMap::default();
+        for (_, path, _) in tiles.iter() {
+            let mut path = *path;
+            if area(&path) < 0f64 {
+                path.reverse();
+            }
+            for (a, b) in edges(&path) {
+                directed.insert((PointKey::from(a), PointKey::from(b)), (a, b));
+            }
+        }
+        let mut leaving = FxHashMap::<_, Vec<_>>::default();
+        for (&(a, b), &(from, to)) in directed.iter() {
+            if !directed.contains_key(&(b, a)) {
+                leaving.entry(a).or_default().push((from, to));
+            }
+        }
+
+        let mut used = FxHashSet::default();
+        let mut starts = leaving.keys().copied().collect::<Vec<_>>();
+        starts.sort_unstable();
+        for start in starts {
+            for &(first, second) in leaving[&start].iter() {
+                if used.contains(&(PointKey::from(first), PointKey::from(second))) {
+                    continue;
+                }
+
+                let mut corners = Vec::new();
+                let (mut from, mut to) = (first, second);
+                while used.insert((PointKey::from(from), PointKey::from(to))) {
+                    corners.push(from);
+
+                    // keep the tiles on the left, turning as sharply right as possible
+                    let angle = |vector: Vector2D<f64>| vector.y.atan2(vector.x);
+                    let back = angle(from - to);
+                    let turn = |next: Point2D<f64>| {
+                        let turn = (back - angle(next - to)).rem_euclid(std::f64::consts::TAU);
+                        if turn < TOLERANCE {
+                            std::f64::consts::TAU
+                        } else {
+                            turn
+                        }
+                    };
+                    let next = leaving.get(&PointKey::from(to)).and_then(|edges| {
+                        edges
+                            .iter()
+                            .map(|&(_, next)| next)
+                            .min_by(|&a, &b| turn(a).partial_cmp(&turn(b)).unwrap())
+                    });
+                    match next {
+                        Some(next) => {
+                            from = to;
+                            to = next;
+                        }
+                        None => break,
+                    }
+                }
+
+                let closed = PointKey::from(from) == PointKey::from(first);
+                if closed && area(&corners) < -TOLERANCE {
+                    violations.push(Violation::Gap(corners));
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use euclid::default::Transform2D;
+    use euclid::Angle;
+
+    use super::*;
+    use crate::constellation::Constellation;
+    use crate::dart::Dart;
+    use crate::fixtures::sun_patch;
+    use crate::kite::Kite;
+
+    #[test]
+    fn legal() {
+        assert_eq!(sun_patch(4).verify(), Ok(()));
+    }
+
+    #[test]
+    fn illegal() {
+        // a hole where a tile surrounded by others used to be
+        let mut tiles = sun_patch(4);
+        let (i, _) = (tiles.kites.iter().enumerate())
+            .min_by(|(_, a), (_, b)| {
+                let length = |kite: &Kite| {
+                    let middle = kite.mapping().transform_point(Point2D::new(0.5, 0f64));
+                    middle.to_vector().length()
+                };
+                length(a).partial_cmp(&length(b)).unwrap()
+            })
+            .unwrap();
+        tiles.kites.remove(i);
+        let violations = tiles.verify().unwrap_err();
+        assert!(matches!(violations.as_slice(), [Violation::Gap(corners)] if corners.len() == 4));
+
+        // the same tile twice
+        let mut tiles = sun_patch(4);
+        tiles.kites.push(tiles.kites[0]);
+        let last = TileIndex::Kite(tiles.kites.len() - 1);
+        let violations = tiles.verify().unwrap_err();
+        assert!(violations.contains(&Violation::Overlap(TileIndex::Kite(0), last)));
+
+        // a dart's nose at a kite's apex, making a rhombus
+        let tiles = MatchList {
+            kites: vec![Kite::new(Transform2D::identity())],
+            darts: vec![Dart::new(
+                Transform2D::rotation(Angle::degrees(72f64)),
+                None,
+                None,
+            )],
+        };
+        let violations = tiles.verify().unwrap_err();
+        assert!(matches!(
+            violations.as_slice(),
+            [Violation::Colour(point), ..] if point.to_vector().length() < TOLERANCE
+        ));
+        assert!(violations
+            .iter()
+            .all(|violation| matches!(violation, Violation::Colour(_))));
+    }
+}